@@ -0,0 +1,14 @@
+import json
+from statistics import mean, median
+
+# reads games written by `gaddag-rust text -n N --json games.jsonl`, one game per line
+s1s, s2s, turns = [], [], []
+with open("../games.jsonl") as file:
+    for line in file:
+        game = json.loads(line)
+        s1, s2 = [p["score"] for p in game["players"]]
+        s1s.append(s1)
+        s2s.append(s2)
+        turns.append(len(game["history"]) - 1)
+
+print(mean(s1s), mean(s2s), mean(turns), len(s1s), median(s1s), median(s2s), median(turns))
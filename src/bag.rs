@@ -1,4 +1,5 @@
 use crate::game::Game;
+use crate::inference::Inference;
 use crate::utils::*;
 use rand::seq::SliceRandom;
 use std::collections::HashMap;
@@ -80,15 +81,37 @@ impl Bag {
     }
 
     pub fn to_str(&self) -> String {
+        self.to_str_marking(&[])
+    }
+
+    /*
+    Like to_str, but the first unseen tiles of each letter are coloured by how many of it
+    the opponent is expected to hold (kept, see Inference::holding), rounded.
+    */
+    pub fn to_str_marking(&self, kept: &[(char, f32)]) -> String {
         let mut res = format!("┌─────{:<03}/100─────┐\n", self.distribution.len());
 
         for (i, c) in self.alph.iter().enumerate() {
             let count = self.distribution.count(*c);
+            let held = kept
+                .iter()
+                .find(|(k, _)| k == c)
+                .map_or(0, |(_, n)| (n.round() as usize).min(count));
+            let marked = if held > 0 {
+                format!(
+                    "{}{}{}",
+                    color::Fg(color::Rgb(230, 120, 0)),
+                    c.to_string().repeat(held),
+                    RESET
+                )
+            } else {
+                String::new()
+            };
 
             res = format!(
-                "{}│ {}{grey}{used}{spaces}{clear} │\n",
+                "{}│ {marked}{}{grey}{used}{spaces}{clear} │\n",
                 res,
-                c.to_string().repeat(count),
+                c.to_string().repeat(count - held),
                 grey = color::Fg(color::Rgb(220, 220, 220)),
                 used = c.to_string().repeat(self.amts[i] - count),
                 spaces = &" ".repeat(15 - self.amts[i]),
@@ -101,12 +124,14 @@ impl Bag {
         res
     }
 
-    pub fn to_str_for_current_player(&self, game: &Game) -> String {
+    // the tiles unseen by the current player, marking what the last mover probably kept if known
+    pub fn to_str_for_current_player(&self, game: &Game, kept: Option<&Inference>) -> String {
         let mut d = self.distribution.clone();
-        for i in game.get_player(((game.current + 1) % 2) as i32).rack.iter() {
-            d.push(*i);
+        for n in (0..game.seats()).filter(|&n| n != game.current) {
+            d.extend(game.get_player(n as i32).rack.iter());
         }
 
-        Bag::with(&d).to_str()
+        let kept = kept.map(|i| i.holding()).unwrap_or_default();
+        Bag::with(&d).to_str_marking(&kept)
     }
 }
@@ -6,9 +6,11 @@ use crate::utils::*;
 use array_init::array_init;
 use itertools::Itertools;
 use petgraph::graph::NodeIndex;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use termion::color;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::fmt;
@@ -27,6 +29,37 @@ fn _as(v: usize) -> i32 {
     i32::try_from(v).unwrap()
 }
 
+/*
+Rule parameters of a game: how many tiles a rack holds,
+and the bonus for playing all of them in one move.
+*/
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Rules {
+    pub rack_size: usize,
+    pub bingo: i32,
+}
+
+impl Rules {
+    pub fn default() -> Rules {
+        Rules {
+            rack_size: 7,
+            bingo: 50,
+        }
+    }
+}
+
+/*
+The saved form of a board: one string per row, with letters (lowercase for blanks)
+and '.' for empty squares, the tiles left in the bag, and the rules.
+The dictionary is reloaded and the cross checks recomputed when it is read back.
+*/
+#[derive(Serialize, Deserialize)]
+struct SavedBoard {
+    rows: Vec<String>,
+    bag: Vec<char>,
+    rules: Rules,
+}
+
 pub struct Board {
     state: [[char; 15]; 15],
     dict: Dictionary,
@@ -35,6 +68,7 @@ pub struct Board {
     pub blanks: Vec<Position>,
     cross_checks: [[Vec<char>; 225]; 2],
     affected: Vec<Position>,
+    pub rules: Rules,
 }
 
 /*
@@ -103,6 +137,7 @@ impl Board {
             blanks: vec![],
             cross_checks: [array_init(|_| Vec::new()), array_init(|_| Vec::new())],
             affected: vec![],
+            rules: Rules::default(),
         };
 
         for di in 0..2 {
@@ -136,6 +171,84 @@ impl Board {
                 self.cross_checks[di][p.to_int()] = chars(self.valid_at(*p, *d));
             }
         }
+        // everything is up to date now
+        self.affected.clear();
+    }
+
+    /*
+    The tiles not on the board or in the rack: the bag and the opponent's rack
+    from the point of view of the player holding the rack.
+    */
+    pub fn unseen(&self, rack: &[char]) -> Vec<char> {
+        let mut pool = Bag::default().distribution;
+        let mut seen = rack.to_vec();
+        for p in positions() {
+            if self.blanks.contains(&p) {
+                seen.push('?');
+            } else if self.is_letter(p) {
+                seen.push(self.at_position(p));
+            }
+        }
+
+        for c in seen {
+            // more of a letter than the distribution has must be unmarked blanks
+            if let Some(i) = pool.iter().position(|x| *x == c) {
+                pool.remove(i);
+            } else if let Some(i) = pool.iter().position(|x| *x == '?') {
+                pool.remove(i);
+            }
+        }
+
+        pool
+    }
+
+    // three scoreless turns each (six with two players) end the game
+    pub fn scoreless_limit(seats: usize) -> u32 {
+        3 * seats as u32
+    }
+
+    pub fn rack_value(&self, rack: &[char]) -> i32 {
+        rack.iter().map(|c| self.bag.score(*c)).sum()
+    }
+
+    // the player who went out, if any: an empty rack with nothing left to draw
+    pub fn went_out(&self, racks: &[&[char]]) -> Option<usize> {
+        if !self.bag.distribution.is_empty() {
+            return None;
+        }
+        racks.iter().position(|r| r.is_empty())
+    }
+
+    /*
+    What each player's score changes by when the game ends with these racks (in seat order).
+    Against one opponent, going out earns twice the value of the other rack; with more, the player
+    going out earns the others' racks and each of them loses their own. When nobody went out
+    (the scoreless turns), every player loses the value of their own rack.
+    */
+    pub fn settle(&self, racks: &[&[char]]) -> Vec<i32> {
+        let values = racks
+            .iter()
+            .map(|r| self.rack_value(r))
+            .collect::<Vec<i32>>();
+        let total: i32 = values.iter().sum();
+        let heads_up = racks.len() == 2;
+
+        let out = self.went_out(racks);
+        values
+            .iter()
+            .enumerate()
+            .map(|(n, v)| match out {
+                Some(o) if o == n && heads_up => 2 * total,
+                Some(o) if o == n => total,
+                Some(_) if heads_up => 0,
+                _ => -v,
+            })
+            .collect()
+    }
+
+    // replaces the superleave table (see leaves::train)
+    pub fn set_leaves(&mut self, leaves: HashMap<Vec<usize>, f32>) {
+        self.dict.set_leaves(leaves);
     }
 
     pub fn reset(&mut self) {
@@ -231,7 +344,7 @@ impl Board {
 
     pub fn place_move(&mut self, m: &Move) -> bool {
         if m.typ == Type::Play {
-            return self.play_word(m.position, m.word.clone(), m.direction, true);
+            return self.play_word(m.position, m.format(false), m.direction, true);
         }
         false
     }
@@ -268,6 +381,10 @@ impl Board {
         cross
     }
 
+    pub fn is_word(&self, word: &str) -> bool {
+        self.dict.check_word(&word.to_string())
+    }
+
     pub fn valid(&self, d: &Direction) -> bool {
         // TODO check connectedness
         // self.get_words(*dir).iter().all(|x| self.dict.check_word(&x.word))
@@ -328,17 +445,21 @@ impl Board {
 
     pub fn valid_move(&mut self, m: &Move) -> bool {
         let state = self.state.clone();
+        let blanks = self.blanks.clone();
+        let affected = self.affected.clone();
         self.place_move(m);
 
         let star = self.state[7][7] == '*';
         let valid = self.all_valid();
         self.state = state;
+        self.blanks = blanks;
+        self.affected = affected;
 
         if star || !valid {
             return false;
         }
 
-        let positions: HashSet<Position> = HashSet::from_iter(m.iter().map(|i| i.0));
+        let positions: HashSet<Position> = HashSet::from_iter(m.tiles.iter().map(|t| t.position));
         let anchors: HashSet<Position> = HashSet::from_iter(self.anchors().iter().map(|i| *i));
 
         if anchors.len() == 0 {
@@ -403,6 +524,99 @@ impl Board {
     pub fn get_board(&self) -> [[char; 15]; 15] {
         return self.state;
     }
+
+    pub fn to_rows(state: &[[char; 15]; 15], blanks: &[Position]) -> Vec<String> {
+        let mut rows = Vec::new();
+        for (row, r) in state.iter().enumerate() {
+            let mut s = String::new();
+            for (col, &c) in r.iter().enumerate() {
+                if !c.is_alphabetic() {
+                    s.push('.');
+                } else if blanks.contains(&Position { row, col }) {
+                    s.push(c.to_ascii_lowercase());
+                } else {
+                    s.push(c);
+                }
+            }
+            rows.push(s);
+        }
+        rows
+    }
+
+    /*
+    Reads a state back from its rows (see to_rows) and bag.
+    Cross checks are not saved, so every square is marked affected
+    and they are recomputed the next time moves are generated.
+    */
+    pub fn state_from_rows(rows: &[String], bag: &[char]) -> Result<S, String> {
+        if rows.len() != 15 {
+            return Err(format!("expected 15 rows, found {}", rows.len()));
+        }
+
+        let mut state = STATE;
+        let mut blanks = Vec::new();
+        for (row, r) in rows.iter().enumerate() {
+            let chars = r.chars().collect::<Vec<char>>();
+            if chars.len() != 15 {
+                return Err(format!("row {} is not 15 squares long", row + 1));
+            }
+            for (col, &c) in chars.iter().enumerate() {
+                if c == '.' {
+                    continue;
+                } else if !c.is_ascii_alphabetic() {
+                    return Err(format!("unknown square '{}' in row {}", c, row + 1));
+                }
+                if c.is_ascii_lowercase() {
+                    blanks.push(Position { row, col });
+                }
+                state[row][col] = c.to_ascii_uppercase();
+            }
+        }
+
+        if let Some(c) = bag.iter().find(|c| !ALPH.contains(**c)) {
+            return Err(format!("unknown tile '{}' in bag", c));
+        }
+
+        Ok(Board::fresh_state(state, blanks, bag.to_vec()))
+    }
+
+    // a state with these tiles and bag, whose cross checks are all recomputed when next needed
+    pub fn fresh_state(state: [[char; 15]; 15], blanks: Vec<Position>, bag: Vec<char>) -> S {
+        let mut cross_checks: [[Vec<char>; 225]; 2] =
+            [array_init(|_| Vec::new()), array_init(|_| Vec::new())];
+        for checks in cross_checks.iter_mut() {
+            for p in positions().iter() {
+                checks[p.to_int()] = chars([true; 26]);
+            }
+        }
+
+        (state, blanks, cross_checks, bag, positions())
+    }
+}
+
+impl Serialize for Board {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        SavedBoard {
+            rows: Board::to_rows(&self.state, &self.blanks),
+            bag: self.bag.distribution.clone(),
+            rules: self.rules,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Board, D::Error> {
+        let saved = SavedBoard::deserialize(deserializer)?;
+        let state = Board::state_from_rows(&saved.rows, &saved.bag).map_err(D::Error::custom)?;
+
+        let mut board = Board::default();
+        board.set_state(&state);
+        board.rules = saved.rules;
+        board.update_cross_checks();
+
+        Ok(board)
+    }
 }
 
 impl Board {
@@ -576,31 +790,44 @@ impl Board {
 
         /*
         Implement exchanges (dirtily) e.g., for all possible exchanges, evaluate.
-        Note that this returns the complement; for example, rack
-        BCDFGHQ, a move with word of "C" means "exchange everything but C".
-        This is for efficiency reasons: exchanging is quite light, but heavy
-        if we must include rack-pruning as well. Therefore, this is done
-        on the side.
+        This iterates over the leaves that could be kept; for example, rack
+        BCDFGHQ with leave "C" means "exchange everything but C".
+        The move itself records the tiles that go back in the bag.
         */
-        if self.bag.distribution.len() > 7 {
+        if self.bag.distribution.len() > self.rules.rack_size {
             for i in 0..rack.len() {
                 for j in rack.iter().cloned().combinations(i) {
                     let jw = to_word(&j);
-                    result.push(Move {
-                        word: j.iter().collect(),
-                        position: Position { row: 0, col: 0 },
-                        direction: Direction::Down,
-                        score: 0,
-                        evaluation: *self.dict.evaluate(&jw).expect(&format!("{:?}", &jw)),
-                        typ: Type::Exch,
-                    });
+                    let mut exchanged = rack.clone();
+                    for c in j.iter() {
+                        exchanged._remove_item(*c);
+                    }
+                    let mut m = Move::exchange(&exchanged);
+                    m.evaluation = self.leave_value(&jw);
+                    result.push(m);
                 }
             }
         }
 
+        /*
+        Passing is always legal, so it is always offered. It keeps the whole rack,
+        so its evaluation is that of the full rack.
+        */
+        let mut pass = Move::pass();
+        pass.evaluation = self.leave_value(&rword);
+        result.push(pass);
+
         result
     }
 
+    fn leave_value(&self, leave: &Vec<usize>) -> f32 {
+        // keeping the whole rack (a pass) draws nothing, so it is worth nothing either
+        if leave.iter().sum::<usize>() >= self.rules.rack_size {
+            return 0.0;
+        }
+        self.dict.evaluate(leave)
+    }
+
     // todo: fix ugly arguments
     fn left_on_board(
         &self,
@@ -842,14 +1069,8 @@ impl Board {
                 if let Some(_terminal) = self.trie.can_next(node, '@') {
                     // move forms a valid word
                     // return move
-                    let mut m = Move {
-                        word: word.to_string(),
-                        position: start_pos,
-                        direction,
-                        score: 0,
-                        evaluation: *self.dict.evaluate(&rack).expect(&format!("{:?}", &rack)),
-                        typ: Type::Play,
-                    };
+                    let mut m = self.make_move(word, start_pos, direction);
+                    m.evaluation = self.leave_value(&rack);
                     m.score = self.score(&m, cross_sums); // score move
                     moves.push(m); // add move to list
                 }
@@ -893,17 +1114,8 @@ impl Board {
                                 );
                             } else if let Some(_terminal) = self.trie.can_next(nnode, '@') {
                                 // try to place move
-                                let mut m = Move {
-                                    word: nword.to_string(),
-                                    position: start_pos,
-                                    direction,
-                                    score: 0,
-                                    evaluation: *self
-                                        .dict
-                                        .evaluate(&nr)
-                                        .expect(&format!("{:?}", &nr)),
-                                    typ: Type::Play,
-                                };
+                                let mut m = self.make_move(nword, start_pos, direction);
+                                m.evaluation = self.leave_value(&nr);
                                 m.score = self.score(&m, cross_sums);
                                 moves.push(m);
                             }
@@ -938,14 +1150,8 @@ impl Board {
                     );
                 } else if let Some(_terminal) = self.trie.can_next(next_node, '@') {
                     // try to place move
-                    let mut m = Move {
-                        word: nword.to_string(),
-                        position: start_pos,
-                        direction,
-                        score: 0,
-                        evaluation: *self.dict.evaluate(&rack).expect(&format!("{:?}", &rack)),
-                        typ: Type::Play,
-                    };
+                    let mut m = self.make_move(nword, start_pos, direction);
+                    m.evaluation = self.leave_value(&rack);
                     m.score = self.score(&m, cross_sums);
                     moves.push(m);
                 }
@@ -953,38 +1159,155 @@ impl Board {
         }
     }
 
-    pub fn reals(&self, m: &Move) -> Vec<char> {
-        let mut result = Vec::new();
-        for (curr_pos, i) in m.iter() {
-            if !self.is_letter(curr_pos) {
-                result.push(i);
+    pub fn make_move(&self, word: &str, position: Position, direction: Direction) -> Move {
+        /*
+        Builds a play from a word written out in full from position (through letters included,
+        lowercase for blanks), telling apart the tiles that are already on the board.
+        */
+        let mut m = Move::none();
+        m.position = position;
+        m.direction = direction;
+
+        let mut p = position;
+        for (i, c) in word.chars().enumerate() {
+            if i > 0 && !p.tick(direction) {
+                break;
             }
+            let on_board = self.is_letter(p);
+            m.tiles.push(Tile {
+                position: p,
+                letter: c.to_ascii_uppercase(),
+                blank: if on_board {
+                    self.blanks.contains(&p)
+                } else {
+                    c.is_lowercase()
+                },
+                on_board,
+            });
         }
 
-        result
+        m
     }
 
-    pub fn format(&self, m: &Move, human: bool) -> String {
-        let mut res = String::new();
-        for (curr_pos, i) in m.iter() {
-            if !self.is_letter(curr_pos) {
-                res.push(i);
-            } else {
-                if human {
-                    res.push('(');
-                    if self.blanks.contains(&curr_pos) {
-                        res.push(i.to_lowercase().nth(0).unwrap());
-                    } else {
-                        res.push(i);
+    /*
+    Inverse of Move::format: turns human notation into a move on the current board.
+    Accepts "8H WORD" (across) and "H8 WORD" (down), with tiles already on the board
+    written as themselves, as "." or in parentheses, and lowercase letters for blanks.
+    "-ABC" exchanges ABC and "-" passes. Plays are checked against the board and
+    the dictionary, and scored; the rack is not checked here.
+    */
+    pub fn parse_move(&mut self, s: &str) -> Result<Move, String> {
+        let s = s.trim();
+
+        if s == "-" {
+            return Ok(Move::pass());
+        }
+
+        if let Some(tiles) = s.strip_prefix('-') {
+            let word = tiles.trim().to_uppercase();
+            if word.is_empty() || !word.chars().all(|c| ALPH.contains(c)) {
+                return Err(format!("cannot exchange {}", tiles));
+            }
+            if self.bag.distribution.len() <= self.rules.rack_size {
+                return Err(String::from("not enough tiles in the bag to exchange"));
+            }
+            return Ok(Move::exchange(&word.chars().collect()));
+        }
+
+        let mut parts = s.split_whitespace();
+        let (pos, word) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(pos), Some(word), None) => (pos, word),
+            _ => return Err(format!("expected a position and a word, got {}", s)),
+        };
+
+        let mut m = self.parse_play(pos, word)?;
+        if !self.valid_move(&m) {
+            return Err(format!("{} {} is not a valid play", pos, word));
+        }
+        m.score = self.breakdown(&m).total;
+
+        Ok(m)
+    }
+
+    /*
+    A play written as for parse_move, checked against the tiles on the board but not against
+    the dictionary, and not scored: recorded games (see gcg) keep their phonies and scores.
+    */
+    pub fn parse_play(&self, pos: &str, word: &str) -> Result<Move, String> {
+        let (start, dir) = match Position::parse(pos) {
+            Some(x) => x,
+            None => return Err(format!("{} is not a position", pos)),
+        };
+
+        let mut result = String::new();
+        let mut n_placed = 0;
+        let mut through = false; // inside parentheses
+        let mut p = start;
+        let mut first = true;
+
+        for c in word.chars() {
+            match c {
+                '(' if !through => {
+                    through = true;
+                    continue;
+                }
+                ')' if through => {
+                    through = false;
+                    continue;
+                }
+                _ => {}
+            }
+
+            if !first && !p.tick(dir) {
+                return Err(format!("{} runs off the board", word));
+            }
+            first = false;
+
+            let on_board = self.is_letter(p);
+            if c == '.' || through {
+                if !on_board {
+                    return Err(format!("no tile on the board at {}", p.to_str(dir)));
+                }
+                if c != '.' && c.to_ascii_uppercase() != self.at_position(p) {
+                    return Err(format!("{} is not on the board at {}", c, p.to_str(dir)));
+                }
+                result.push(self.at_position(p));
+            } else if c.is_ascii_alphabetic() {
+                if on_board {
+                    if c.to_ascii_uppercase() != self.at_position(p) {
+                        return Err(format!("{} is already taken", p.to_str(dir)));
                     }
-                    res.push(')');
+                    result.push(self.at_position(p));
                 } else {
-                    res.push('.');
+                    result.push(c);
+                    n_placed += 1;
                 }
+            } else {
+                return Err(format!("unexpected {} in {}", c, word));
             }
         }
 
-        res.replace(")(", "")
+        if through {
+            return Err(format!("unclosed parenthesis in {}", word));
+        }
+        if n_placed == 0 {
+            return Err(String::from("a play must place at least one tile"));
+        }
+
+        let mut before = start;
+        let mut after = p;
+        if (before.tick_opp(dir) && self.is_letter(before))
+            || (after.tick(dir) && self.is_letter(after))
+        {
+            return Err(format!("{} does not include all adjacent tiles", word));
+        }
+        if result.len() < 2 {
+            return Err(String::from(
+                "write a single tile in the direction of the word it forms",
+            ));
+        }
+
+        Ok(self.make_move(&result, start, dir))
     }
 
     pub fn score_without_sums(&mut self, m: &mut Move) {
@@ -1039,13 +1362,111 @@ impl Board {
 
         let mut score = true_mult * true_score + total_cross_score;
 
-        if m.word.len() - n_played == 7 {
-            score += 50;
+        if m.tiles.len() - n_played == self.rules.rack_size {
+            score += self.rules.bingo;
         }
 
         score
     }
 
+    fn letter_value(&self, p: Position, c: char) -> i32 {
+        // value of a letter at a position, placed or already on the board; blanks are worth 0
+        if c.is_lowercase() || self.blanks.contains(&p) {
+            return 0;
+        }
+        self.bag.score(c)
+    }
+
+    fn shown_at(&self, p: Position) -> char {
+        // letter on the board, lowercase if it is a blank
+        let c = self.at_position(p);
+        if self.blanks.contains(&p) {
+            return c.to_lowercase().nth(0).unwrap();
+        }
+        c
+    }
+
+    fn multipliers(&self, p: Position) -> (i32, i32) {
+        // (letter multiplier, word multiplier) of an empty square
+        match self.at_position(p) {
+            '*' | '^' => (1, 2),
+            '#' => (1, 3),
+            '+' => (3, 1),
+            '-' => (2, 1),
+            _ => (1, 1),
+        }
+    }
+
+    /*
+    Scores a move word by word, before it is placed on the board.
+    This follows the same rules as score, but walks the cross-words itself
+    instead of relying on cross-sums, so it is slower and meant for display.
+    */
+    pub fn breakdown(&self, m: &Move) -> Breakdown {
+        let mut b = Breakdown::none();
+        if m.typ != Type::Play {
+            return b;
+        }
+
+        let cross_dir = m.direction.flip();
+        let mut n_placed = 0;
+
+        for (curr_pos, i) in m.iter() {
+            if self.is_letter(curr_pos) {
+                // already on the board, so only counts towards the main word
+                let c = self.shown_at(curr_pos);
+                b.main.push(c, self.letter_value(curr_pos, c), 1);
+                continue;
+            }
+
+            n_placed += 1;
+            let value = self.letter_value(curr_pos, i);
+            let (letter_mult, word_mult) = self.multipliers(curr_pos);
+            b.main.push(i, value, letter_mult);
+            b.main.word_mult *= word_mult;
+
+            let mut start = curr_pos;
+            loop {
+                let mut prev = start;
+                if prev.tick_opp(cross_dir) && self.is_letter(prev) {
+                    start = prev;
+                } else {
+                    break;
+                }
+            }
+
+            let mut cross = WordScore::new();
+            cross.word_mult = word_mult;
+            let mut p = start;
+            loop {
+                if p == curr_pos {
+                    cross.push(i, value, letter_mult);
+                } else if self.is_letter(p) {
+                    let c = self.shown_at(p);
+                    cross.push(c, self.letter_value(p, c), 1);
+                } else {
+                    break;
+                }
+                if !p.tick(cross_dir) {
+                    break;
+                }
+            }
+
+            if cross.letters.len() > 1 {
+                cross.tally();
+                b.cross.push(cross);
+            }
+        }
+
+        b.main.tally();
+        if n_placed == self.rules.rack_size {
+            b.bingo = self.rules.bingo;
+        }
+        b.total = b.main.score + b.cross.iter().map(|w| w.score).sum::<i32>() + b.bingo;
+
+        b
+    }
+
     pub fn to_termion(&self) -> String {
         let sep = "-".repeat(66);
 
@@ -1169,6 +1590,153 @@ impl fmt::Display for Board {
         write!(f, "\n{}\n", bot)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn squares(m: &Move) -> Vec<(usize, usize)> {
+        m.tiles
+            .iter()
+            .map(|t| (t.position.row, t.position.col))
+            .collect()
+    }
+
+    #[test]
+    fn row_first_is_across() {
+        let mut board = Board::default();
+
+        let across = board.parse_move("8H CAT").unwrap();
+        assert_eq!(across.direction, Direction::Across);
+        assert_eq!(squares(&across), vec![(7, 7), (7, 8), (7, 9)]);
+
+        let down = board.parse_move("H8 CAT").unwrap();
+        assert_eq!(down.direction, Direction::Down);
+        assert_eq!(squares(&down), vec![(7, 7), (8, 7), (9, 7)]);
+
+        assert_eq!(across.score, 10);
+        assert_eq!(down.score, 10);
+    }
+
+    #[test]
+    fn positions_round_trip() {
+        for p in positions() {
+            for &d in Direction::iter() {
+                assert_eq!(Position::parse(&p.to_str(d)), Some((p, d)));
+                assert_eq!(Position::parse(&p.to_str(d).to_lowercase()), Some((p, d)));
+            }
+        }
+    }
+
+    #[test]
+    fn notation_round_trips() {
+        let mut board = Board::default();
+        let cat = board.parse_move("8H CAT").unwrap();
+        board.place_move(&cat);
+
+        let rack = "EIRS?".chars().collect::<Vec<char>>();
+        let moves = board.gen_all_moves(&rack, false);
+        assert!(moves.iter().any(|m| m.tiles.iter().any(|t| t.blank)));
+        for m in moves.iter().filter(|m| m.typ == Type::Play) {
+            let parsed = board.parse_move(&m.notation(true)).unwrap();
+            assert_eq!(&parsed, m, "{}", m.notation(true));
+            assert_eq!(parsed.score, m.score, "{}", m.notation(true));
+            assert_eq!(parsed.notation(true), m.notation(true));
+        }
+    }
+
+    #[test]
+    fn lowercase_is_a_blank() {
+        let mut board = Board::default();
+        let m = board.parse_move("8H CaT").unwrap();
+
+        let blanks = m.tiles.iter().map(|t| t.blank).collect::<Vec<bool>>();
+        assert_eq!(blanks, vec![false, true, false]);
+        assert_eq!(m.tiles[1].letter, 'A');
+        assert_eq!(m.score, 8);
+        assert_eq!(m.notation(true), "8H CaT");
+    }
+
+    fn parts(b: &Breakdown) -> i32 {
+        b.main.score + b.cross.iter().map(|w| w.score).sum::<i32>() + b.bingo
+    }
+
+    // the breakdown adds up, to what score gives
+    fn check_breakdown(board: &mut Board, m: &Move) -> Breakdown {
+        let b = board.breakdown(m);
+        let mut scored = m.clone();
+        board.score_without_sums(&mut scored);
+        assert_eq!(parts(&b), b.total, "{}", m.notation(true));
+        assert_eq!(b.total, scored.score, "{}", m.notation(true));
+        b
+    }
+
+    #[test]
+    fn breakdown_sums_to_the_score() {
+        let mut board = Board::default();
+        let bingo = board.parse_move("8D RETAINS").unwrap();
+        let b = check_breakdown(&mut board, &bingo);
+        assert_eq!((b.bingo, b.total), (50, 66));
+        board.place_move(&bingo);
+
+        let rack = "EILOT?".chars().collect::<Vec<char>>();
+        let moves = board.gen_all_moves(&rack, false);
+        let (mut crossing, mut blanks) = (0, 0);
+        for m in moves.iter().filter(|m| m.typ == Type::Play) {
+            let b = check_breakdown(&mut board, m);
+            assert_eq!(b.total, m.score, "{}", m.notation(true));
+            if !b.cross.is_empty() {
+                crossing += 1;
+            }
+            if m.tiles.iter().any(|t| t.blank && !t.on_board) {
+                blanks += 1;
+            }
+        }
+        assert!(crossing > 0 && blanks > 0);
+
+        // a blank on the board scores nothing in the words through it
+        let m = board.parse_move("D7 aRE").unwrap();
+        let b = check_breakdown(&mut board, &m);
+        assert_eq!(b.main.word, "aRE");
+        board.place_move(&m);
+        let m = board.parse_move("7C ZA").unwrap();
+        let b = check_breakdown(&mut board, &m);
+        assert_eq!((b.main.word.as_str(), b.total), ("Za", 20));
+    }
+
+    #[test]
+    fn bad_positions_are_rejected() {
+        for s in [
+            "", "8", "H", "0H", "H0", "16H", "H16", "8P", "P8", "8HH", "HH8", "88", "-8H", "8 H",
+            "\u{e9}8", "8\u{e9}", "\u{df}8", "8\u{df}",
+        ]
+        .iter()
+        {
+            assert_eq!(Position::parse(s), None, "{}", s);
+        }
+
+        let mut board = Board::default();
+        for s in [
+            "8P CAT",
+            "16H CAT",
+            "H0 CAT",
+            "8H",
+            "8H CAT DOG",
+            "8M CATS",
+            "O8 CAT",
+            "8H C(AT",
+            "8H C)AT",
+            "8H C4T",
+            "8H (CAT)",
+            "8H C",
+            "A1 CAT",
+        ]
+        .iter()
+        {
+            assert!(board.parse_move(s).is_err(), "{}", s);
+        }
+    }
+}
 /*
 ------------------------------------------------------------------
 |    | A | B | C | D | E | F | G | H | I | J | K | L | M | N | O |
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+// points lost for each started minute a player runs over
+pub const OVERTIME_PENALTY: i32 = 10;
+
+/*
+How much time each player has for the game, and what they get back after each of their moves.
+Written like "25" (minutes) or "25+5" (and seconds added per move).
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeControl {
+    pub initial: Duration,
+    pub increment: Duration,
+}
+
+impl TimeControl {
+    pub fn parse(s: &str) -> Result<TimeControl, String> {
+        let mut parts = s.splitn(2, '+');
+        let minutes = parts.next().unwrap_or("");
+        let initial = minutes
+            .parse::<f32>()
+            .map_err(|_| format!("{} is not a number of minutes", minutes))?;
+        let increment = match parts.next() {
+            Some(seconds) => seconds
+                .parse::<f32>()
+                .map_err(|_| format!("{} is not a number of seconds", seconds))?,
+            None => 0.0,
+        };
+        if initial <= 0.0 || increment < 0.0 {
+            return Err(format!("{} is not a time control", s));
+        }
+
+        Ok(TimeControl {
+            initial: Duration::from_secs_f32(initial * 60.0),
+            increment: Duration::from_secs_f32(increment),
+        })
+    }
+}
+
+/*
+A chess clock for every player: the player to move uses up their time until they move,
+then gets the increment. Time left goes below zero in overtime, which costs
+OVERTIME_PENALTY points per started minute at the end of the game (see penalty).
+Times are in milliseconds.
+*/
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Clock {
+    pub control: TimeControl,
+    left: Vec<i64>,
+    #[serde(default)]
+    pub first: usize, // the first move it timed (see Game::get_move), for clocks set mid-game
+    #[serde(skip)]
+    since: Option<Instant>, // when the player to move started, None while stopped
+}
+
+impl Clock {
+    pub fn new(control: TimeControl, seats: usize) -> Clock {
+        Clock {
+            control,
+            left: vec![control.initial.as_millis() as i64; seats],
+            first: 0,
+            since: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Clock::new(self.control, self.left.len());
+    }
+
+    pub fn start(&mut self) {
+        self.since = Some(Instant::now());
+    }
+
+    pub fn stop(&mut self) {
+        self.since = None;
+    }
+
+    fn running(&self) -> i64 {
+        self.since.map_or(0, |s| s.elapsed().as_millis() as i64)
+    }
+
+    // player n moved: their time so far is used up, and the next player's starts
+    pub fn punch(&mut self, n: usize) {
+        let used = self.running();
+        self.left[n] += self.control.increment.as_millis() as i64 - used;
+        self.start();
+    }
+
+    /*
+    Takes back player n's last move, which took used milliseconds (see Game::withdraw):
+    that time runs on, so it counts towards whatever they do instead.
+    */
+    pub fn undo(&mut self, n: usize, used: i64) {
+        self.left[n] += used - self.control.increment.as_millis() as i64;
+        let since = Instant::now() - Duration::from_millis((used + self.running()) as u64);
+        self.since = Some(since);
+    }
+
+    // what player n has left, counting the time they are taking now if they are to move
+    pub fn left(&self, n: usize, to_move: bool) -> i64 {
+        if to_move {
+            self.left[n] - self.running()
+        } else {
+            self.left[n]
+        }
+    }
+
+    pub fn penalty(&self, n: usize) -> i32 {
+        if self.left[n] >= 0 {
+            return 0;
+        }
+        let started = (-self.left[n] + 59_999) / 60_000;
+        OVERTIME_PENALTY * started as i32
+    }
+
+    /*
+    How long player n should think about their next move: an even share of their time
+    over the moves they probably have left (about four tiles a move), plus the increment.
+    In overtime, just the increment, or a second without one.
+    */
+    pub fn allot(&self, n: usize, bag: usize, seats: usize) -> Duration {
+        let increment = self.control.increment.as_millis() as i64;
+        let left = self.left(n, true);
+        if left <= 0 {
+            return Duration::from_millis(increment.max(1000) as u64);
+        }
+        let moves = bag / (4 * seats) + 2;
+        Duration::from_millis((left / moves as i64 + increment).min(left) as u64)
+    }
+
+    // e.g. "24:31", or "-1:05" in overtime
+    pub fn format(ms: i64) -> String {
+        let sign = if ms < 0 { "-" } else { "" };
+        let s = ms.abs() / 1000;
+        format!("{}{}:{:02}", sign, s / 60, s % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn penalty_per_started_minute() {
+        let mut clock = Clock::new(TimeControl::parse("25").unwrap(), 2);
+        for &(over, penalty) in [(0, 0), (1, 10), (60, 10), (61, 20)].iter() {
+            clock.left[0] = -over * 1000;
+            assert_eq!(clock.penalty(0), penalty, "{} s over", over);
+        }
+        clock.left[0] = -1;
+        assert_eq!(clock.penalty(0), OVERTIME_PENALTY);
+        assert_eq!(clock.penalty(1), 0);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::utils::to_word;
+use crate::leaves::{self, Table};
 use crate::utils::ALPH;
 use indicatif::ProgressBar;
 use indicatif::ProgressIterator;
@@ -6,12 +6,15 @@ use petgraph::graph::{Edges, NodeIndex};
 use petgraph::visit::EdgeRef;
 use petgraph::Direction;
 use petgraph::{Directed, Graph}; // todo use daggy?
-use rayon::prelude::*;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs;
+use std::sync::Mutex;
+
+// the word list resources/ is split from (nwl18.txt, by dipththin.py), for gcg headers
+pub const LEXICON: &str = "NWL2018";
 
 fn load_from_file<T: DeserializeOwned + Serialize>(file: &str, callback: fn() -> T) -> T {
     match fs::read(file) {
@@ -35,6 +38,19 @@ fn load_from_file<T: DeserializeOwned + Serialize>(file: &str, callback: fn() ->
     }
 }
 
+// leaves given with --leaves, used instead of the table cached in dict.ser
+static LEAVES: Mutex<Option<Table>> = Mutex::new(None);
+
+/*
+Reads a leaves file (see leaves::read) for every dictionary loaded from now on.
+*/
+pub fn use_leaves(path: &str) -> Result<(), String> {
+    let table = leaves::read(path)?;
+    eprintln!("Loaded {} leaves from {}", table.len(), path);
+    *LEAVES.lock().unwrap() = Some(table);
+    Ok(())
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Dictionary {
     words: HashMap<char, HashMap<char, HashSet<String>>>,
@@ -43,7 +59,7 @@ pub struct Dictionary {
 
 impl Dictionary {
     pub fn default() -> Dictionary {
-        load_from_file("dict.ser", || {
+        let mut dict = load_from_file("dict.ser", || {
             let mut dict = Dictionary {
                 words: HashMap::new(),
                 leaves: HashMap::new(),
@@ -74,19 +90,11 @@ impl Dictionary {
 
             let bar = ProgressBar::new(40);
 
-            dict.leaves = fs::read_to_string("resources/leaves.txt")
-                .expect("No leaves file")
-                .lines()
-                .map(String::from)
-                .collect::<Vec<String>>()
-                .par_iter()
-                .map(|line| {
-                    let s: Vec<&str> = line.split(" ").collect();
-                    let word = to_word(&s[0].chars().collect());
-                    let eval = s[1].parse::<f32>().unwrap();
-                    (word, eval)
-                })
-                .collect();
+            // without a table every leave is valued heuristically (see evaluate)
+            dict.leaves = leaves::read("resources/leaves.txt").unwrap_or_else(|e| {
+                println!("{}, using heuristic leave values", e);
+                HashMap::new()
+            });
 
             dict.leaves.insert(
                 vec![
@@ -97,7 +105,12 @@ impl Dictionary {
             bar.finish();
 
             dict
-        })
+        });
+
+        if let Some(table) = LEAVES.lock().unwrap().as_ref() {
+            dict.leaves = table.clone();
+        }
+        dict
     }
 
     pub fn check_word(&self, word: &String) -> bool {
@@ -110,8 +123,16 @@ impl Dictionary {
         false
     }
 
-    pub fn evaluate(&self, rack: &Vec<usize>) -> Option<&f32> {
-        self.leaves.get(rack)
+    pub fn set_leaves(&mut self, leaves: HashMap<Vec<usize>, f32>) {
+        self.leaves = leaves;
+    }
+
+    // the superleave table's value if it has the leave, the heuristic one otherwise
+    pub fn evaluate(&self, rack: &Vec<usize>) -> f32 {
+        match self.leaves.get(rack) {
+            Some(v) => *v,
+            None => leaves::heuristic(rack),
+        }
     }
 }
 
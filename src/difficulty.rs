@@ -0,0 +1,203 @@
+use crate::board::Board;
+use crate::strategy::{ranked, Budget, Strategy};
+use crate::utils::{positions, Move, Type};
+
+use rand::{thread_rng, Rng};
+use std::collections::HashSet;
+use std::f32::consts::PI;
+use std::fs;
+use std::sync::Arc;
+
+// words by frequency, most common first, one per line; for the levels with a limited vocabulary
+const FREQUENCY: &str = "resources/frequency.txt";
+// tiles in a game, to spread a target score over
+const TILES: f32 = 100.0;
+// equity given up per point a move would put it ahead of its target
+const STEER: f32 = 1.0;
+
+/*
+How a human of some level plays: which words they know, how far off their judgement is,
+how often they see bingos, and whether they exchange. target is the average score per
+game the profile is tuned to; the AI plays down towards it when it gets ahead, but
+cannot play above its own strength (about 400 a game).
+*/
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Profile {
+    pub name: &'static str,
+    pub max_length: usize,     // longest word it knows
+    pub common: Option<usize>, // only knows this many of the most common words, given a frequency list
+    pub noise: f32,            // standard deviation of the error in its equity, in points
+    pub miss_bingo: f32,       // chance of not seeing bingos on a turn
+    pub exchanges: bool,
+    pub target: u32,
+}
+
+pub const PROFILES: [Profile; 4] = [
+    Profile {
+        name: "beginner",
+        max_length: 5,
+        common: Some(5000),
+        noise: 10.0,
+        miss_bingo: 0.95,
+        exchanges: false,
+        target: 220,
+    },
+    Profile {
+        name: "junior",
+        max_length: 6,
+        common: Some(20000),
+        noise: 6.0,
+        miss_bingo: 0.7,
+        exchanges: false,
+        target: 300,
+    },
+    Profile {
+        name: "club",
+        max_length: 8,
+        common: Some(60000),
+        noise: 3.0,
+        miss_bingo: 0.3,
+        exchanges: true,
+        target: 370,
+    },
+    Profile {
+        name: "expert",
+        max_length: 15,
+        common: None,
+        noise: 1.0,
+        miss_bingo: 0.05,
+        exchanges: true,
+        target: 400,
+    },
+];
+
+pub fn profile(name: &str) -> Option<Profile> {
+    PROFILES.iter().find(|p| p.name == name).cloned()
+}
+
+// None, with a warning, when there is no frequency list to read
+fn known_words(n: usize) -> Option<HashSet<String>> {
+    match fs::read_to_string(FREQUENCY) {
+        Ok(s) => Some(s.lines().take(n).map(|w| w.trim().to_uppercase()).collect()),
+        Err(e) => {
+            eprintln!(
+                "could not read {} (words by frequency, most common first): {}; \
+                 only limiting words by length",
+                FREQUENCY, e
+            );
+            None
+        }
+    }
+}
+
+// normally distributed, by Box-Muller
+fn gaussian(sd: f32) -> f32 {
+    let mut rng = thread_rng();
+    let u1: f32 = rng.gen_range(f32::EPSILON, 1.0);
+    let u2: f32 = rng.gen();
+    sd * (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/*
+Plays like a person of the given profile (see Profile).
+*/
+#[derive(Clone)]
+pub struct Level {
+    pub profile: Profile,
+    words: Option<Arc<HashSet<String>>>, // None when every word of a known length is known
+    scored: u32,                         // so far this game
+    on_board: usize,                     // tiles on the board at its last turn
+}
+
+impl Level {
+    // without a frequency list, a profile with a limited vocabulary knows every word up to its max_length
+    pub fn new(profile: Profile) -> Level {
+        let words = profile.common.and_then(known_words).map(Arc::new);
+        Level {
+            profile,
+            words,
+            scored: 0,
+            on_board: 0,
+        }
+    }
+
+    fn knows(&self, board: &Board, m: &Move) -> bool {
+        let b = board.breakdown(m);
+        let mut words = b.cross.iter().map(|w| &w.word).collect::<Vec<&String>>();
+        words.push(&b.main.word);
+
+        words.iter().all(|w| {
+            w.len() <= self.profile.max_length
+                && match &self.words {
+                    Some(known) => known.contains(&w.to_uppercase()),
+                    None => true,
+                }
+        })
+    }
+}
+
+impl Strategy for Level {
+    fn name(&self) -> String {
+        format!("level:{}", self.profile.name)
+    }
+
+    fn choose(&mut self, board: &mut Board, rack: &[char], _budget: Budget) -> Move {
+        let on_board = positions()
+            .into_iter()
+            .filter(|&p| board.is_letter(p))
+            .count();
+        if on_board < self.on_board {
+            // fewer tiles than last turn: a new game
+            self.scored = 0;
+        }
+        self.on_board = on_board;
+
+        let (moves, eval_val) = ranked(board, rack, false);
+        let sees_bingos = !thread_rng().gen_bool(self.profile.miss_bingo as f64);
+        // on target, its score keeps up with the share of tiles played
+        let per_tile = self.profile.target as f32 / TILES;
+
+        let judged = moves
+            .iter()
+            .filter(|m| self.profile.exchanges || m.typ != Type::Exch)
+            .filter(|m| {
+                sees_bingos
+                    || m.tiles.iter().filter(|t| !t.on_board).count() < board.rules.rack_size
+            })
+            .filter(|m| m.typ != Type::Play || self.knows(board, m))
+            .map(|m| (m.eval(1.0, eval_val) + gaussian(self.profile.noise), m))
+            .collect::<Vec<(f32, &Move)>>();
+        let by_value = |a: &(f32, &Move), b: &(f32, &Move)| a.0.partial_cmp(&b.0).unwrap();
+
+        /*
+        Easing off only picks between scoring plays: passes, exchanges and zero-point plays
+        are played when it would anyway, never to keep the score down.
+        */
+        let best = match judged.iter().cloned().max_by(by_value) {
+            Some((_, m)) if m.score > 0 => judged
+                .iter()
+                .filter(|(_, m)| m.score > 0)
+                .map(|&(v, m)| {
+                    let placed = m.tiles.iter().filter(|t| !t.on_board).count();
+                    // points it can score and stay on target; once ahead, all of them count against it
+                    let pace =
+                        (per_tile * (on_board + placed) as f32 - self.scored as f32).max(0.0);
+                    let over = (m.score as f32 - pace).max(0.0);
+                    (v - STEER * over, m)
+                })
+                .max_by(by_value),
+            best => best,
+        };
+
+        let m = match best {
+            Some((_, m)) => Move::of(m),
+            None => Move::pass(),
+        };
+        self.scored += m.score as u32;
+        m
+    }
+
+    fn box_clone(&self) -> Box<dyn Strategy> {
+        Box::new(self.clone())
+    }
+}
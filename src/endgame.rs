@@ -0,0 +1,371 @@
+use crate::board::Board;
+use crate::simulate::read_board;
+use crate::strategy::Budget;
+use crate::utils::{ItemRemovable, Move, Type};
+
+use std::time::Duration;
+
+// limits for the AI when it switches to the solver by itself
+pub const DEPTH: usize = 8;
+pub const TIME: Duration = Duration::from_secs(3);
+
+/*
+The result of an endgame search, from the point of view of the side to move:
+value is the spread it can force over the rest of the game (including the
+points for going out), pv the moves both sides play to get it.
+If exact is false the search stopped at `depth` plies and value is an estimate.
+*/
+pub struct Solution {
+    pub value: i32,
+    pub pv: Vec<Move>,
+    pub depth: usize,
+    pub exact: bool,
+    pub nodes: usize,
+}
+
+impl Solution {
+    pub fn best(&self) -> Move {
+        match self.pv.first() {
+            Some(m) => Move::of(m),
+            None => Move::pass(),
+        }
+    }
+
+    pub fn to_str(&self) -> String {
+        format!(
+            "{:+} ({}depth {}, {} nodes): {}",
+            self.value,
+            if self.exact { "exact, " } else { "" },
+            self.depth,
+            self.nodes,
+            self.pv
+                .iter()
+                .map(|m| m.notation(true))
+                .collect::<Vec<String>>()
+                .join("; ")
+        )
+    }
+}
+
+struct Search<'a> {
+    board: &'a mut Board,
+    racks: [Vec<char>; 2],
+    budget: Budget,
+    nodes: usize,
+    horizon: bool, // some line was cut off by the depth limit
+    timed_out: bool,
+    first: Option<Move>, // best move of the last iteration, searched first
+}
+
+impl<'a> Search<'a> {
+    fn rack_value(&self, side: usize) -> i32 {
+        self.board.rack_value(&self.racks[side])
+    }
+
+    // the spread for side once the game is over as the racks stand (see Board::settle)
+    fn settled(&self, side: usize) -> i32 {
+        let changes = self.board.settle(&[&self.racks[0], &self.racks[1]]);
+        changes[side] - changes[(side + 1) % 2]
+    }
+
+    fn leave(rack: &[char], m: &Move) -> Vec<char> {
+        let mut rack = rack.to_vec();
+        for c in m.reals() {
+            if rack.contains(&c) {
+                rack._remove_item(c);
+            } else {
+                rack._remove_item('?');
+            }
+        }
+        rack
+    }
+
+    fn ordered(&mut self, side: usize) -> Vec<Move> {
+        let mut moves = self
+            .board
+            .gen_all_moves(&self.racks[side], false)
+            .into_iter()
+            .filter(|m| m.typ != Type::Exch)
+            .collect::<Vec<Move>>();
+        // highest scores first, then longer plays (closer to going out), passes last
+        moves.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then(b.tiles.len().cmp(&a.tiles.len()))
+        });
+        moves.dedup();
+        if let Some(first) = &self.first {
+            if let Some(i) = moves.iter().position(|m| m == first) {
+                let m = moves.remove(i);
+                moves.insert(0, m);
+            }
+        }
+        moves
+    }
+
+    /*
+    Negamax with alpha-beta pruning. Returns the value for `side` and the line that gets it.
+    */
+    fn negamax(
+        &mut self,
+        side: usize,
+        depth: usize,
+        mut alpha: i32,
+        beta: i32,
+        passed: bool,
+    ) -> (i32, Vec<Move>) {
+        self.nodes += 1;
+        let other = (side + 1) % 2;
+
+        if depth == 0 {
+            // whoever is left holding more points is worse off
+            self.horizon = true;
+            return (self.rack_value(other) - self.rack_value(side), vec![]);
+        }
+        if self.budget.spent(self.nodes) {
+            self.timed_out = true;
+            return (0, vec![]);
+        }
+
+        let moves = self.ordered(side);
+        self.first = None; // only the root gets the hint
+
+        let mut best = (i32::MIN, vec![]);
+        for m in moves {
+            let saved = self.board.save_state();
+            let rack = self.racks[side].clone();
+
+            let (value, line) = if m.is_pass() {
+                if passed {
+                    // both passed in a row: the game ends and each side loses its rack
+                    (self.settled(side), vec![])
+                } else {
+                    let (v, line) = self.negamax(other, depth - 1, -beta, -alpha, true);
+                    (-v, line)
+                }
+            } else {
+                self.board.place_move(&m);
+                self.racks[side] = Search::leave(&rack, &m);
+                if self.racks[side].is_empty() {
+                    // went out
+                    (m.score + self.settled(side), vec![])
+                } else {
+                    // m.score - v has to land in (alpha, beta), so v in (m.score - beta, m.score - alpha)
+                    let (v, line) = self.negamax(
+                        other,
+                        depth - 1,
+                        m.score.saturating_sub(beta),
+                        m.score.saturating_sub(alpha),
+                        false,
+                    );
+                    (m.score - v, line)
+                }
+            };
+
+            self.board.set_state(&saved);
+            self.racks[side] = rack;
+            if self.timed_out {
+                return (0, vec![]);
+            }
+
+            if value > best.0 {
+                let mut pv = vec![m];
+                pv.extend(line);
+                best = (value, pv);
+            }
+            if value > alpha {
+                alpha = value;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best
+    }
+}
+
+/*
+Solves the endgame for `rack` to move against `opp_rack` with the bag empty,
+deepening one ply at a time until the result is exact, `max_depth` is reached, or the budget runs out.
+The first ply is always searched in full, so there is a value even with no time left.
+The board is left as it was.
+*/
+pub fn solve(
+    board: &mut Board,
+    rack: &[char],
+    opp_rack: &[char],
+    max_depth: usize,
+    budget: Budget,
+) -> Solution {
+    let mut search = Search {
+        board,
+        racks: [rack.to_vec(), opp_rack.to_vec()],
+        budget,
+        nodes: 0,
+        horizon: false,
+        timed_out: false,
+        first: None,
+    };
+
+    let mut solution = Solution {
+        value: 0,
+        pv: search.ordered(0).into_iter().take(1).collect(),
+        depth: 0,
+        exact: false,
+        nodes: 0,
+    };
+
+    for depth in 1..=max_depth {
+        search.budget = if depth == 1 {
+            Budget::unlimited()
+        } else {
+            budget
+        };
+        search.horizon = false;
+        search.first = solution.pv.first().map(Move::of);
+        let (value, pv) = search.negamax(0, depth, i32::MIN + 1, i32::MAX, false);
+        if search.timed_out {
+            break;
+        }
+
+        solution = Solution {
+            value,
+            pv,
+            depth,
+            exact: !search.horizon,
+            nodes: search.nodes,
+        };
+        if solution.exact {
+            break;
+        }
+    }
+
+    solution.nodes = search.nodes;
+    solution
+}
+
+// the move the AI plays once the bag is empty, when the opponent's rack is whatever is unseen
+pub fn best_move(board: &mut Board, rack: &[char], budget: Budget) -> Move {
+    let opp_rack = board.unseen(rack);
+    solve(board, rack, &opp_rack, DEPTH, budget.at_most(TIME)).best()
+}
+
+/*
+Reads a board from stdin (see simulate::read_board) and prints the solution
+for rack to move. Without opp_rack, the opponent holds every unseen tile.
+*/
+pub fn main(rack: String, opp_rack: Option<String>, max_depth: usize, time: u64) {
+    let mut board = read_board();
+    board.bag.distribution = vec![];
+    let rack = rack.to_uppercase().chars().collect::<Vec<char>>();
+    let opp_rack = match opp_rack {
+        Some(r) => r.to_uppercase().chars().collect::<Vec<char>>(),
+        None => board.unseen(&rack),
+    };
+    println!("{}", board);
+    println!(
+        "{} to move against {}",
+        rack.iter().collect::<String>(),
+        opp_rack.iter().collect::<String>()
+    );
+
+    let solution = solve(
+        &mut board,
+        &rack,
+        &opp_rack,
+        max_depth,
+        Budget::time(Duration::from_secs(time)),
+    );
+    println!("{}", solution.to_str());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the same search as Search::negamax, without pruning
+    fn minimax(
+        board: &mut Board,
+        racks: &mut [Vec<char>; 2],
+        side: usize,
+        depth: usize,
+        passed: bool,
+    ) -> i32 {
+        let other = (side + 1) % 2;
+        let left = |board: &Board, racks: &[Vec<char>; 2]| {
+            board.rack_value(&racks[other]) - board.rack_value(&racks[side])
+        };
+        if depth == 0 {
+            return left(board, racks);
+        }
+
+        let moves = board.gen_all_moves(&racks[side], false);
+        let mut best = i32::MIN;
+        for m in moves.into_iter().filter(|m| m.typ != Type::Exch) {
+            let value = if m.is_pass() {
+                if passed {
+                    left(board, racks)
+                } else {
+                    -minimax(board, racks, other, depth - 1, true)
+                }
+            } else {
+                let saved = board.save_state();
+                let rack = racks[side].clone();
+                board.place_move(&m);
+                racks[side] = Search::leave(&rack, &m);
+                let value = if racks[side].is_empty() {
+                    m.score + 2 * board.rack_value(&racks[other])
+                } else {
+                    m.score - minimax(board, racks, other, depth - 1, false)
+                };
+                board.set_state(&saved);
+                racks[side] = rack;
+                value
+            };
+            best = best.max(value);
+        }
+        best
+    }
+
+    // endgames where pruning on the wrong window once got the value wrong
+    const POSITIONS: [(&str, &str, &str); 3] = [
+        (
+            "NCB",
+            "OU",
+            r#"["...............","...............","...R...........","...E...........","...ZA..........",
+            "....D..........","....E.........C","...ON..T......O","...PO..H......N","...ES..I......T",
+            "..GRIEVED.OATER","...AS..FOAMY..A","...............","...............","..............."]"#,
+        ),
+        (
+            "LVB",
+            "RO",
+            r#"["...............","...............","............A..","..JAW..I....N..","....EMERIES.N..",
+            ".......O.HOKES.",".....CINQ.TIX..",".......E.......","......FRONTER..","...............",
+            "...............","...............","...............","...............","..............."]"#,
+        ),
+        (
+            "IIO",
+            "UV",
+            r#"["...............","...............",".........DEFY..",".....O..JETE...",".....OR.I......",
+            ".....HE.n......","......VOX......","......OI.......","......I........","......c........",
+            "......ER.......",".......AW......",".......ZA......",".......EM......",".......DE......"]"#,
+        ),
+    ];
+
+    #[test]
+    fn pruning_keeps_the_minimax_value() {
+        for (rack, opp_rack, rows) in POSITIONS.iter() {
+            let json = format!(
+                r#"{{"rows": {}, "bag": [], "rules": {{"rack_size": 7, "bingo": 50}}}}"#,
+                rows
+            );
+            let mut board: Board = serde_json::from_str(&json).unwrap();
+            let racks: [Vec<char>; 2] = [rack.chars().collect(), opp_rack.chars().collect()];
+
+            let solution = solve(&mut board, &racks[0], &racks[1], 3, Budget::unlimited());
+            let expected = minimax(&mut board, &mut racks.clone(), 0, solution.depth, false);
+            assert_eq!(solution.value, expected, "{} against {}", rack, opp_rack);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::board::Board;
+use crate::defense::{Defense, Threats};
+use crate::utils::{Move, Type};
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/*
+How static equity values a move:
+    score * points + leave weight * leave value
+    - lanes * triple-word lanes opened - hot * hot spots opened - lines * bingo lines opened
+where the leave weight shrinks as the bag empties, following 1 - 1 / (curve * tiles in bag),
+and what is opened is found by defense::Defense.
+
+Written as json in a file, or inline as comma-separated key=value pairs
+(e.g. "leave=0.8,lanes=4"); missing keys keep their defaults.
+Only StaticEquity plays by a given config; everything else ranks with the defaults (see strategy::ranked).
+*/
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Equity {
+    pub score: f32,
+    pub leave: f32,
+    pub curve: f32,
+    pub lanes: f32,
+    pub hot: f32,
+    pub lines: f32,
+}
+
+impl Default for Equity {
+    fn default() -> Equity {
+        Equity {
+            score: 1.0,
+            leave: 1.0,
+            curve: 4.0,
+            lanes: 3.0,
+            hot: 2.0,
+            lines: 0.5,
+        }
+    }
+}
+
+impl Equity {
+    /*
+    A file name, or key=value pairs (see above).
+    */
+    pub fn parse(spec: &str) -> Result<Equity, String> {
+        if !spec.contains('=') {
+            let s =
+                fs::read_to_string(spec).map_err(|e| format!("could not read {}: {}", spec, e))?;
+            return serde_json::from_str(&s).map_err(|e| format!("{}: {}", spec, e));
+        }
+
+        let mut equity = Equity::default();
+        for pair in spec.split(',') {
+            let mut kv = pair.splitn(2, '=');
+            let (k, v) = (kv.next().unwrap().trim(), kv.next().unwrap_or("").trim());
+            let v = v
+                .parse::<f32>()
+                .map_err(|_| format!("bad value for {}: {}", k, v))?;
+            match k {
+                "score" => equity.score = v,
+                "leave" => equity.leave = v,
+                "curve" => equity.curve = v,
+                "lanes" => equity.lanes = v,
+                "hot" => equity.hot = v,
+                "lines" => equity.lines = v,
+                _ => return Err(format!("unknown equity weight {}", k)),
+            }
+        }
+
+        Ok(equity)
+    }
+
+    // inline form of the config, for naming strategies
+    pub fn to_spec(self) -> String {
+        format!(
+            "score={},leave={},curve={},lanes={},hot={},lines={}",
+            self.score, self.leave, self.curve, self.lanes, self.hot, self.lines
+        )
+    }
+
+    pub fn leave_weight(&self, bag: usize) -> f32 {
+        /*
+        https://www.desmos.com/calculator/lkrdbcoiqt
+        Essentially, the idea is for eval to be roughly 1 the whole game,
+        but be lower as the bag decreases and exchanging/longetivity becomes impossible.
+        This method will have its primary effect to counteract blankholding.
+        */
+        let x = 1.0 - 1.0 / (self.curve * bag as f32);
+        if x > 0.0 {
+            return self.leave * x;
+        }
+        0.0
+    }
+
+    // equity given up for what a play opens
+    pub fn penalty(&self, t: Threats) -> f32 {
+        self.lanes * t.lanes as f32
+            + self.hot * t.hot_spots as f32
+            + self.lines * t.bingo_lines as f32
+    }
+
+    /*
+    Returns: every move for the rack, best equity first, and the weight given to leaves
+    */
+    pub fn ranked(&self, board: &mut Board, rack: &[char], eff: bool) -> (Vec<Move>, f32) {
+        let gen = board.gen_all_moves(&rack.to_vec(), eff);
+        let eval_val = self.leave_weight(board.bag.distribution.len()); // todo implement if bag is empty, empty rack
+
+        let defends = self.lanes != 0.0 || self.hot != 0.0 || self.lines != 0.0;
+        let defense = if defends {
+            Some(Defense::new(board))
+        } else {
+            None
+        };
+        let mut valued = gen
+            .into_iter()
+            .map(|m| {
+                let mut v = m.eval(self.score, eval_val);
+                if let (Some(defense), Type::Play) = (&defense, m.typ) {
+                    v -= self.penalty(defense.threats(board, &m));
+                }
+                (v, m)
+            })
+            .collect::<Vec<(f32, Move)>>();
+        valued.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let mut gen = valued.into_iter().map(|(_, m)| m).collect::<Vec<Move>>();
+        gen.dedup();
+        gen.reverse();
+
+        (gen, eval_val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opening_a_lane_ranks_below_a_closed_play() {
+        let rows = r#"["...............","...............","............A..","..JAW..I....N..","....EMERIES.N..",
+        ".......O.HOKES.",".....CINQ.TIX..",".......E.......","......FRONTER..","...............",
+        "...............","...............","...............","...............","..............."]"#;
+        let json = format!(
+            r#"{{"rows": {}, "bag": [], "rules": {{"rack_size": 7, "bingo": 50}}}}"#,
+            rows
+        );
+        let mut board: Board = serde_json::from_str(&json).unwrap();
+        let rack = "DELT".chars().collect::<Vec<char>>();
+
+        let equity = Equity::default();
+        let (ranked, _) = equity.ranked(&mut board, &rack, false);
+        let defense = Defense::new(&board);
+        let threats = |m: &Move| defense.threats(&board, m);
+
+        // plays of the same score (the leave counts for nothing with the bag empty),
+        // one opening a lane, the other opening nothing
+        let mut pairs = 0;
+        for (i, open) in ranked.iter().enumerate() {
+            if open.typ != Type::Play || threats(open).lanes == 0 {
+                continue;
+            }
+            for (j, closed) in ranked.iter().enumerate() {
+                if closed.typ == Type::Play
+                    && closed.score == open.score
+                    && threats(closed) == Threats::default()
+                {
+                    assert!(j < i, "{:?} ranked above {:?}", open, closed);
+                    pairs += 1;
+                }
+            }
+        }
+        assert!(pairs > 0);
+    }
+}
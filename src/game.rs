@@ -1,21 +1,172 @@
 use crate::bag::Bag;
-use crate::board::{Board, S, STATE};
+use crate::board::{Board, Rules, S, STATE};
+use crate::clock::{Clock, TimeControl};
+use crate::gcg;
+use crate::inference::{self, Inference};
 use crate::player::Player;
 use crate::splice;
-use crate::utils::{rack_to_string, Move, Type};
+use crate::strategy::{ranked, Budget, StaticEquity, Strategy};
+use crate::utils::{positions, rack_to_string, Breakdown, ItemRemovable, Move, Type};
 
 use array_init::array_init;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use std::fs;
+use std::time::{Duration, Instant};
 use std::vec::Vec;
 
+// bump when the saved format changes (1 kept the board after every move)
+const SAVE_VERSION: u32 = 2;
+// seats at a table; four racks leave 72 tiles to draw
+pub const MAX_PLAYERS: usize = 4;
+
 pub struct Game {
-    players: [Player; 2],
+    players: Vec<Player>,
     board: Board,
     pub current: usize,
     turn: u32,
     pub finished: bool,
-    states: Vec<(S, Move, Vec<char>, f32)>,
+    scoreless: u32,
+    history: Vec<Turn>, // the first is a placeholder before any move (see start_turn)
     pub state: usize,
+    adjustments: Vec<(usize, usize, Adjustment)>, // after how many moves, for which player
+    move_time: Option<Duration>,                  // how long the AI may think about each move
+    clock: Option<Clock>,
+    since: Instant, // when the player to move started their turn
+}
+
+/*
+One entry of the game history: the move, the rack it was played from, the tiles drawn
+after it, the skill difference (None until it is first asked for, see Game::skill) and how long
+the turn took in milliseconds (None if unknown, e.g. read from a file).
+Boards are not kept but replayed (see Game::position).
+*/
+#[derive(Clone, Serialize, Deserialize)]
+struct Turn {
+    #[serde(rename = "move")]
+    m: Move,
+    rack: Vec<char>,
+    #[serde(default)]
+    drawn: Vec<char>,
+    #[serde(default)]
+    skill: Option<f32>,
+    #[serde(default)]
+    time: Option<u64>,
+}
+
+impl Turn {
+    // what was left on the rack before drawing
+    fn leave(&self) -> Vec<char> {
+        let tiles = self
+            .m
+            .reals()
+            .iter()
+            .map(|&c| if c.is_lowercase() { '?' } else { c })
+            .collect::<Vec<char>>();
+        without(self.rack.clone(), &tiles)
+    }
+}
+
+// the tiles, less one of each gone that is among them
+fn without(mut tiles: Vec<char>, gone: &[char]) -> Vec<char> {
+    for c in gone {
+        if let Some(i) = tiles.iter().position(|x| x == c) {
+            tiles.remove(i);
+        }
+    }
+    tiles
+}
+
+// generic over the board so saving can borrow it
+#[derive(Serialize, Deserialize)]
+struct SavedGame<B> {
+    version: u32,
+    players: Vec<Player>,
+    board: B,
+    current: usize,
+    turn: u32,
+    finished: bool,
+    scoreless: u32,
+    history: Vec<Turn>,
+    state: usize,
+    #[serde(default)]
+    adjustments: Vec<(usize, usize, Adjustment)>,
+    #[serde(default)]
+    clock: Option<Clock>,
+}
+
+/*
+A change to a player's score outside their own moves: a play challenged off (it stays
+in the history as a pass), a bonus for a play challenged without success, or a time penalty.
+*/
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Adjustment {
+    Withdrawn(Move),
+    Challenge(i32),
+    Time(i32),
+}
+
+impl Adjustment {
+    // what it adds to the score the moves add up to
+    pub fn points(&self) -> i32 {
+        match self {
+            Adjustment::Withdrawn(_) => 0,
+            Adjustment::Challenge(p) | Adjustment::Time(p) => *p,
+        }
+    }
+}
+
+/*
+How a game ended (see Game::finish): who went out, if anyone did (otherwise it ended on
+scoreless turns), the tiles left on each rack, and what each score changed by at the end.
+*/
+#[derive(Clone, Debug)]
+pub struct Ending {
+    pub out: Option<usize>,
+    pub racks: Vec<String>,
+    pub changes: Vec<i32>,
+}
+
+impl Ending {
+    // every rack but player n's
+    fn others(&self, n: usize) -> String {
+        let mut others = self.racks.clone();
+        others.remove(n);
+        others.concat()
+    }
+
+    /*
+    What player n gained or lost at the end, e.g. "2*(AEI) +6" for going out against one opponent,
+    "(AEIQ) +16" against more, or "(QZ) -20" for tiles left over; None if nothing.
+    */
+    pub fn notation(&self, n: usize) -> Option<String> {
+        let heads_up = self.racks.len() == 2;
+        match self.out {
+            Some(o) if o == n && heads_up => {
+                Some(format!("2*({}) +{}", self.others(n), self.changes[n]))
+            }
+            Some(o) if o == n => Some(format!("({}) +{}", self.others(n), self.changes[n])),
+            Some(_) if heads_up => None,
+            _ => Some(format!("({}) {:+}", self.racks[n], self.changes[n])),
+        }
+    }
+
+    /*
+    The same as a gcg line for player n, with their total after it: going out counts
+    the other racks, and any other player left with tiles counts their own.
+    */
+    pub fn to_gcg(&self, n: usize, name: &str, total: i32) -> Option<String> {
+        self.notation(n)?;
+        let racks = match self.out {
+            Some(o) if o == n => format!("({})", self.others(n)),
+            _ => format!("{r} ({r})", r = self.racks[n]),
+        };
+        Some(format!(
+            ">{}: {} {:+} {}",
+            name, racks, self.changes[n], total
+        ))
+    }
 }
 
 impl Game {
@@ -24,20 +175,35 @@ impl Game {
     }
 
     pub fn with(name1: String, name2: String) -> Game {
-        let mut board = Board::default();
-        let player_1 = Player {
-            rack: board.bag.draw_tiles(7),
-            name: name1,
-            score: 0,
-        };
-        let player_2 = Player {
-            rack: board.bag.draw_tiles(7),
-            name: name2,
-            score: 0,
-        };
-        let players = [player_1, player_2];
+        Game::with_rules(name1, name2, Rules::default())
+    }
 
-        let dist = board.bag.distribution.clone();
+    pub fn with_rules(name1: String, name2: String, rules: Rules) -> Game {
+        Game::with_players(vec![name1, name2], rules)
+    }
+
+    /*
+    A game for two to four players (see MAX_PLAYERS), who move in the order given.
+    Every player starts as static equity; see set_strategies.
+    */
+    pub fn with_players(names: Vec<String>, rules: Rules) -> Game {
+        assert!(
+            (2..=MAX_PLAYERS).contains(&names.len()),
+            "a game needs 2 to {} players",
+            MAX_PLAYERS
+        );
+        let mut board = Board::default();
+        board.rules = rules;
+        let players = names
+            .into_iter()
+            .map(|name| {
+                Player::new(
+                    name,
+                    board.bag.draw_tiles(rules.rack_size),
+                    Some(Box::new(StaticEquity::default())),
+                )
+            })
+            .collect::<Vec<Player>>();
 
         Game {
             players,
@@ -45,38 +211,239 @@ impl Game {
             current: 0,
             turn: 1,
             finished: false,
-            states: vec![(
-                (
-                    STATE,
-                    vec![],
-                    [array_init(|_| Vec::new()), array_init(|_| Vec::new())],
-                    dist,
-                    vec![],
-                ),
-                Move::none(),
-                vec![],
-                0.0f32,
-            )],
+            scoreless: 0,
+            history: vec![Game::start_turn()],
+            adjustments: vec![],
             state: 1,
+            move_time: None,
+            clock: None,
+            since: Instant::now(),
         }
     }
 
+    // the history entry before the first move
+    fn start_turn() -> Turn {
+        Turn {
+            m: Move::none(),
+            rack: vec![],
+            drawn: vec![],
+            skill: Some(0.0),
+            time: None,
+        }
+    }
+
+    // the move the current player just made from rack, with what they drew after it
+    fn record(&mut self, m: &Move, rack: Vec<char>, skill: Option<f32>) {
+        let mut turn = Turn {
+            m: Move::of(m),
+            rack,
+            drawn: vec![],
+            skill,
+            time: Some(self.since.elapsed().as_millis() as u64),
+        };
+        turn.drawn = without(self.players[self.current].rack.clone(), &turn.leave());
+        self.history.push(turn);
+    }
+
+    // one per player, in seat order
+    pub fn set_strategies(&mut self, strategies: Vec<Option<Box<dyn Strategy>>>) {
+        for (p, s) in self.players.iter_mut().zip(strategies) {
+            p.strategy = s;
+        }
+    }
+
+    pub fn set_move_time(&mut self, time: Option<Duration>) {
+        self.move_time = time;
+    }
+
+    // a clock for every player (see Clock), starting now; None to play untimed
+    pub fn set_clock(&mut self, control: Option<TimeControl>) {
+        self.clock = control.map(|c| {
+            let mut clock = Clock::new(c, self.players.len());
+            clock.first = self.history.len();
+            clock.start();
+            clock
+        });
+    }
+
+    pub fn clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+
     pub fn set_board(&mut self, board: [[char; 15]; 15]) {
         // for simulation
         self.board.set_board(board);
     }
 
-    pub fn do_move(&mut self, difficulty: usize, eff: bool) -> (Move, String, String, usize) {
+    pub fn do_move(&mut self, eff: bool) -> (Move, String, String) {
         let r = self.get_current_player().rack.clone();
-        let m = self.players[self.current].do_move(&mut self.board, difficulty, eff);
-        self.states
-            .push((self.board.save_state(), Move::of(&m.0), r, 0.0f32));
+        let mut budget = match self.move_time {
+            Some(t) => Budget::time(t),
+            None => Budget::unlimited(),
+        };
+        if let Some(c) = &self.clock {
+            let bag = self.board.bag.distribution.len();
+            budget = budget.at_most(c.allot(self.current, bag, self.players.len()));
+        }
+        let heads_up = self.players.len() == 2;
+        let before = self.watched();
+        let m = self.players[self.current].do_move(&mut self.board, eff, budget, heads_up);
+        self.record(&m.0, r, Some(0.0));
+        self.observe(&m.0, before);
+        self.count_scoreless(&m.0);
         self.tick();
         m
     }
 
+    fn watchers(&self) -> Vec<usize> {
+        // the other players whose strategies infer from the current player's moves
+        (0..self.players.len())
+            .filter(|&o| o != self.current)
+            .filter(|&o| {
+                self.players[o]
+                    .strategy
+                    .as_ref()
+                    .is_some_and(|s| s.infers())
+            })
+            .collect()
+    }
+
+    fn watched(&self) -> Option<S> {
+        // the board before the current player's move, if anyone is to be shown it (see observe)
+        if self.watchers().is_empty() {
+            None
+        } else {
+            Some(self.board.save_state())
+        }
+    }
+
+    fn observe(&mut self, m: &Move, before: Option<S>) {
+        // shows the move just made to every watching strategy, on the board from before it
+        let before = match before {
+            Some(b) => b,
+            None => return,
+        };
+        let after = self.board.save_state();
+        for other in self.watchers() {
+            if let Some(mut s) = self.players[other].strategy.take() {
+                let pool = self.board.unseen(&self.players[other].rack);
+                self.board.set_state(&before);
+                s.observe(&mut self.board, m, &pool);
+                self.board.set_state(&after);
+                self.players[other].strategy = Some(s);
+            }
+        }
+    }
+
+    /*
+    What the player who moved last probably kept (see inference::infer).
+    */
+    pub fn infer(&mut self, samples: usize) -> Option<Inference> {
+        if self.state < 2 {
+            return None;
+        }
+
+        let m = self.get_move(self.state - 1);
+        let pool = self.board.unseen(&self.get_current_player().rack);
+        let now = self.board.save_state();
+        self.board.set_state(&self.position(self.state - 2));
+        let inference = inference::infer(&mut self.board, &m, &pool, samples, Budget::unlimited());
+        self.board.set_state(&now);
+
+        inference
+    }
+
+    fn count_scoreless(&mut self, m: &Move) {
+        // passes, exchanges and zero-point plays all count towards the scoreless ending
+        if m.score == 0 {
+            self.scoreless += 1;
+        } else {
+            self.scoreless = 0;
+        }
+    }
+
+    pub fn scoreless_turns(&self) -> u32 {
+        self.scoreless
+    }
+
+    fn untick(&mut self) {
+        if self.current == 0 {
+            self.turn -= 1;
+        }
+        self.current = (self.current + self.players.len() - 1) % self.players.len();
+        self.state -= 1;
+    }
+
+    /*
+    Takes back the last move after a successful challenge: the board, the bag and the player's rack
+    and score go back to how they were, and they lose their turn (recorded as a pass).
+    */
+    pub fn withdraw(&mut self) {
+        if self.history.len() < 2 {
+            return;
+        }
+
+        let Turn {
+            m,
+            rack,
+            drawn,
+            time,
+            ..
+        } = self.history.pop().unwrap();
+        self.untick();
+        let mut bag = self.board.bag.distribution.clone();
+        self.board.set_state(&self.get_last_state());
+
+        // the tiles drawn after the move go back in the bag (it may have changed since, see gcg)
+        let p = &mut self.players[self.current];
+        p.rack = rack;
+        bag.extend(drawn);
+        if m.exch() {
+            for c in m.reals() {
+                bag._remove_item(c);
+            }
+        }
+        self.board.bag.distribution = bag;
+        p.score -= m.score;
+        // the time the move took counts towards whatever they do instead
+        let taken = Duration::from_millis(time.unwrap_or(0));
+        self.since = self.since.checked_sub(taken).unwrap_or(self.since);
+        self.adjustments
+            .push((self.history.len(), self.current, Adjustment::Withdrawn(m)));
+        // the clock only gives back the time of a move it timed
+        let n = self.history.len();
+        if let (Some(c), Some(used)) = (&mut self.clock, time) {
+            if n >= c.first {
+                c.undo(self.current, used as i64);
+            }
+        }
+        self.scoreless = self
+            .history
+            .iter()
+            .skip(1)
+            .rev()
+            .take_while(|t| t.m.score == 0)
+            .count() as u32;
+
+        self.force_move(&Move::pass());
+    }
+
+    // a bonus or penalty for player n, after the moves made so far
+    pub fn adjust(&mut self, n: usize, a: Adjustment) {
+        self.players[n].score += a.points();
+        self.adjustments.push((self.history.len() - 1, n, a));
+    }
+
+    pub fn adjustments(&self) -> &Vec<(usize, usize, Adjustment)> {
+        &self.adjustments
+    }
+
     pub fn tick(&mut self) {
-        self.current = (self.current + 1) % 2;
+        if let Some(c) = &mut self.clock {
+            c.punch(self.current);
+        }
+        self.since = Instant::now();
+        self.current = (self.current + 1) % self.players.len();
         if self.current == 0 {
             self.turn += 1;
         }
@@ -84,51 +451,115 @@ impl Game {
     }
 
     pub fn force_move(&mut self, m: &Move) {
-        let p = self.get_current_player().clone();
-        let r = p.rack.clone();
-
-        let k = p.gen_moves(&mut self.board, true).0;
-        let p = k.iter().position(|i| *i == *m).unwrap();
-        let d = f32::abs(k.iter().nth(0).unwrap().evaluation - k.iter().nth(p).unwrap().evaluation);
+        let r = self.get_current_player().rack.clone();
+        let before = self.watched();
 
-        self.players[self.current].remove(&mut self.board, &m);
-        self.players[self.current].score += m.score as u32;
-        self.board.place_move(m);
+        self.players[self.current].play(&mut self.board, m);
 
-        self.states
-            .push((self.board.save_state(), Move::of(&m), r, d));
+        // the skill difference waits until it is shown (see skill), so imported games load quickly
+        self.record(m, r, None);
+        self.observe(m, before);
+        self.count_scoreless(m);
         self.tick();
     }
 
-    pub fn finish(&mut self) -> (String, i32, i32) {
-        let mut n = 0;
-        if self.get_player(1).rack.len() == 0 {
-            n = 1;
-        }
-
-        let mut end = 0;
-        let mut end_s = String::new();
+    /*
+    Parses human notation (see Board::parse_move) into a move for the current player,
+    checking that their rack holds the tiles it uses.
+    */
+    pub fn parse_move(&mut self, s: &str) -> Result<Move, String> {
+        let m = self.board.parse_move(s)?;
+        let tiles = m.reals();
 
-        for s in self.get_player((n + 1) % 2).rack.clone() {
-            end += self.board.bag.score(s);
-            end_s.push(s);
+        let p = self.get_current_player();
+        if !p.has_tiles(&tiles) {
+            return Err(format!(
+                "{} does not have {}",
+                p.name,
+                tiles.iter().collect::<String>()
+            ));
         }
 
-        end *= 2;
-        let p = &mut self.players[n as usize];
+        Ok(m)
+    }
+
+    /*
+    Settles the racks once the game is over (see Board::settle). On a clock, it stops, and whoever
+    ran over loses points for it (see Clock::penalty). Only changes the scores the first time it is called.
+    */
+    pub fn finish(&mut self) -> Ending {
+        let ending = self.ending();
         if !self.finished {
-            p.score += end as u32;
+            for (p, change) in self.players.iter_mut().zip(ending.changes.iter()) {
+                p.score += change;
+            }
+            if let Some(c) = &mut self.clock {
+                c.stop();
+                for n in 0..self.players.len() {
+                    let penalty = c.penalty(n);
+                    if penalty > 0 {
+                        self.players[n].score -= penalty;
+                        self.adjustments.push((
+                            self.history.len() - 1,
+                            n,
+                            Adjustment::Time(-penalty),
+                        ));
+                    }
+                }
+            }
         }
-
         self.finished = true;
 
-        (end_s, end, n)
+        ending
+    }
+
+    // how the game ends (or ended) as the racks stand, without settling them
+    pub fn ending(&self) -> Ending {
+        let racks = self
+            .players
+            .iter()
+            .map(|p| p.rack.as_slice())
+            .collect::<Vec<&[char]>>();
+
+        Ending {
+            out: self.board.went_out(&racks),
+            racks: racks.iter().map(|r| r.iter().collect()).collect(),
+            changes: self.board.settle(&racks),
+        }
     }
 
     pub fn is_over(&self) -> bool {
-        self.finished
-            || !(self.board.bag.distribution.len() > 0
-                || (self.players[0].rack.len() > 0 && self.players[1].rack.len() > 0))
+        let went_out = self.board.bag.distribution.is_empty()
+            && self.players.iter().any(|p| p.rack.is_empty());
+        self.finished || self.scoreless >= Board::scoreless_limit(self.players.len()) || went_out
+    }
+
+    // the player with the most points, or None if it is shared
+    pub fn winner(&self) -> Option<usize> {
+        let best = self.players.iter().map(|p| p.score).max()?;
+        let mut top = (0..self.players.len()).filter(|&n| self.players[n].score == best);
+        match (top.next(), top.next()) {
+            (Some(n), None) => Some(n),
+            _ => None,
+        }
+    }
+
+    // e.g. "p1 wins 412 - 380", or "tie 400 - 400"; scores in seat order
+    pub fn result(&self) -> String {
+        let scores = self
+            .players
+            .iter()
+            .map(|p| p.score.to_string())
+            .collect::<Vec<String>>()
+            .join(" - ");
+        match self.winner() {
+            Some(n) => format!("{} wins {}", self.players[n].name, scores),
+            None => format!("tie {}", scores),
+        }
+    }
+
+    pub fn seats(&self) -> usize {
+        self.players.len()
     }
 
     pub fn get_board(&self) -> &Board {
@@ -153,18 +584,108 @@ impl Game {
         &mut self.players[n as usize]
     }
 
-    pub fn set_state(&mut self, to: usize) -> (Move, Vec<char>, f32) {
-        let (s, m, r, skill) = &self.states[to];
+    /*
+    The board after the first n moves, replayed from the history: their tiles go down in order, and
+    the bag holds whatever is neither on the board nor on a rack. Each rack is what its player held
+    at their next move, or holds now, which stays right when racks were set from a gcg file.
+    Cross checks are left to be recomputed, as for a loaded board.
+    */
+    fn position(&self, n: usize) -> S {
+        let mut state = STATE;
+        let mut blanks = Vec::new();
+        for t in self.history.iter().take(n + 1) {
+            if t.m.typ == Type::Play {
+                for tile in t.m.tiles.iter().filter(|t| !t.on_board) {
+                    state[tile.position.row][tile.position.col] = tile.letter;
+                    if tile.blank {
+                        blanks.push(tile.position);
+                    }
+                }
+            }
+        }
 
-        self.board.set_state(s);
+        let seats = self.players.len();
+        let mut seen = Vec::new();
+        for seat in 0..seats {
+            let next = (n + 1..self.history.len()).find(|i| (i - 1) % seats == seat);
+            seen.extend(match next {
+                Some(i) => &self.history[i].rack,
+                None => &self.players[seat].rack,
+            });
+        }
+        for p in positions() {
+            if blanks.contains(&p) {
+                seen.push('?');
+            } else if state[p.row][p.col].is_alphabetic() {
+                seen.push(state[p.row][p.col]);
+            }
+        }
+        let bag = without(Bag::default().distribution, &seen);
+
+        Board::fresh_state(state, blanks, bag)
+    }
+
+    // shows the board after the move numbered to, and returns that move and its rack
+    pub fn set_state(&mut self, to: usize) -> (Move, Vec<char>) {
+        self.board.set_state(&self.position(to));
         self.state = to;
-        self.current = (to - 1) % 2;
+        self.current = (to - 1) % self.players.len();
+
+        let t = &self.history[to];
+        (Move::of(&t.m), t.rack.clone())
+    }
+
+    /*
+    The skill difference of the nth move: how far its equity falls short of the best play from the
+    same rack, on the board it was made on. Worked out the first time it is asked for, then kept.
+    Moves the generator does not produce (e.g. a blank played while holding its letter) have none.
+    */
+    pub fn skill(&mut self, n: usize) -> f32 {
+        if let Some(d) = self.history[n].skill {
+            return d;
+        }
+
+        let (m, rack) = (self.get_move(n), self.get_rack(n));
+        let now = self.board.save_state();
+        self.board.set_state(&self.position(n - 1));
+        let k = ranked(&mut self.board, &rack, true).0;
+        self.board.set_state(&now);
+
+        let d = match k.iter().position(|i| *i == m) {
+            Some(p) => f32::abs(k[0].evaluation - k[p].evaluation),
+            None => 0.0,
+        };
+        self.history[n].skill = Some(d);
+        d
+    }
+
+    pub fn breakdown(&mut self, n: usize) -> Breakdown {
+        // score breakdown of the nth move, scored on the board as it was before the move
+        let m = self.get_move(n);
+        let now = self.board.save_state();
+        self.board.set_state(&self.position(n - 1));
+        let b = self.board.breakdown(&m);
+        self.board.set_state(&now);
 
-        (Move::of(m), r.clone(), *skill)
+        b
+    }
+
+    // the nth move (the first is 1)
+    pub fn get_move(&self, n: usize) -> Move {
+        Move::of(&self.history[n].m)
     }
 
     pub fn get_rack(&self, n: usize) -> Vec<char> {
-        self.states[n].2.clone()
+        self.history[n].rack.clone()
+    }
+
+    // how long the nth move took in milliseconds, if known
+    pub fn get_time(&self, n: usize) -> Option<u64> {
+        self.history[n].time
+    }
+
+    pub fn set_time(&mut self, n: usize, time: Option<u64>) {
+        self.history[n].time = time;
     }
 
     pub fn get_last_state(&self) -> S {
@@ -178,114 +699,402 @@ impl Game {
             );
         }
 
-        self.states[self.state - 1].0.clone()
+        self.position(self.state - 1)
     }
 
     pub fn reset(&mut self) {
         self.board.reset();
         for p in &mut self.players {
             p.score = 0;
-            p.rack = self.board.bag.draw_tiles(7);
+            p.rack = self.board.bag.draw_tiles(self.board.rules.rack_size);
         }
         self.current = 0;
         self.turn = 1;
         self.finished = false;
-        self.states = Vec::new();
-        self.state = 0;
+        self.scoreless = 0;
+        self.history = vec![Game::start_turn()];
+        self.adjustments.clear();
+        if let Some(c) = &mut self.clock {
+            c.reset();
+            c.start();
+        }
+        self.since = Instant::now();
+        self.state = 1;
     }
 
+    // positions in the history: before the first move, and after each one
     pub fn states(&self) -> usize {
-        self.states.len()
+        self.history.len()
+    }
+
+    // of each player on the score sheet: two players get 38 characters each, more share the same width
+    pub fn column_width(&self) -> usize {
+        let seats = self.players.len();
+        (2 * 38 + 2 - seats) / seats
     }
 
     fn states_str(&mut self) -> String {
-        let l = 38;
+        let seats = self.players.len();
+        let l = self.column_width();
+        let rule = |left: &str, mid: &str, right: &str| {
+            format!(
+                "{}─────{}{}{}\n",
+                left,
+                mid,
+                vec!["─".repeat(l); seats].join(mid),
+                right
+            )
+        };
+        let names = self
+            .players
+            .iter()
+            .map(|p| format!("{:^l$}", p.name, l = l))
+            .collect::<Vec<String>>();
         let mut res = format!(
-            "┌─────┬{}┬{}┐\n│     │{:^l$}│{:^l$}│\n├─────┼{}┼{}┤\n",
-            "─".repeat(l),
-            "─".repeat(l),
-            self.get_player(0).name,
-            self.get_player(1).name,
-            "─".repeat(l),
-            "─".repeat(l),
-            l = l
+            "{}│     │{}│\n{}",
+            rule("┌", "┬", "┐"),
+            names.join("│"),
+            rule("├", "┼", "┤")
         );
-        let mut scores = [0, 0];
+        let mut scores = vec![0; seats];
+        // only wide columns show the skill difference
+        let skills = if l >= 38 {
+            (1..self.states()).map(|n| self.skill(n)).collect()
+        } else {
+            vec![]
+        };
 
+        let mut cells = Vec::new();
         for i in 0..(self.states() - 1) {
-            let (m, _r, d) = self.set_state(i + 1);
-            self.board.set_state(&self.get_last_state());
-            scores[i % 2] += m.score;
-
-            let mut num = String::new();
-            if i % 2 == 0 {
-                num = format!("│ {:<02}. │", (i / 2) + 1);
+            // moves know their own tiles, so the sheet can be written without replaying the board
+            let m = &self.history[i + 1].m;
+            scores[i % seats] += m.score;
+            for (_, n, a) in self
+                .adjustments
+                .iter()
+                .filter(|(after, _, _)| *after == i + 1)
+            {
+                scores[*n] += a.points();
             }
 
             let mut s = m.position.to_str(m.direction);
             if m.typ == Type::Exch {
                 s = "EXC".to_string();
+            } else if m.typ == Type::Pass {
+                s = "PAS".to_string();
             }
 
-            res = format!(
-                "{}{} {:<3}: {:<12} +{:<03}/{:<03} [{:0>7}] │",
-                res,
-                num,
-                s,
-                self.board.format(&m, true),
-                m.score,
-                scores[i % 2],
-                format!("{:.4}", d)
-            );
-
-            if i % 2 == 1 {
-                res = format!("{}\n", res);
-            }
+            let cell = if l >= 38 {
+                format!(
+                    " {:<3}: {:<12} +{:<03}/{:<03} [{:0>7}] ",
+                    s,
+                    m.format(true),
+                    m.score,
+                    scores[i % seats],
+                    format!("{:.4}", skills[i])
+                )
+            } else {
+                // no room for the skill difference, and long words are cut short
+                let w = l - 10;
+                let text = format!("{} {}", s, m.format(true));
+                format!(
+                    " {:<w$} +{:<03}/{:<03}",
+                    text.chars().take(w).collect::<String>(),
+                    m.score,
+                    scores[i % seats],
+                    w = w
+                )
+            };
+            cells.push(format!("{:<l$}", cell, l = l));
         }
 
-        if (self.states() - 1) % 2 == 1 {
-            res = format!("{}{}│\n", res, " ".repeat(l));
+        for (k, row) in cells.chunks(seats).enumerate() {
+            let mut row = row.to_vec();
+            row.resize(seats, " ".repeat(l));
+            res = format!("{}│ {:<02}. │{}│\n", res, k + 1, row.join("│"));
         }
 
-        self.state = self.states();
-        self.current = (self.state - 1) % 2;
-        self.board.set_state(&self.get_last_state());
-
-        for _ in (self.states() / 2)..28 {
-            res = format!("{}│     │{}│{}│\n", res, " ".repeat(l), " ".repeat(l));
+        let blank = vec![" ".repeat(l); seats].join("│");
+        for _ in cells.len().div_ceil(seats)..28 {
+            res = format!("{}│     │{}│\n", res, blank);
         }
 
         if self.is_over() {
-            let (end_s, end, n) = self.finish();
-            let mut text = format!("2*({}) +{}/{}", end_s, end, self.get_player(n).score);
-            let n = l - 1 - text.len();
-            if self.states() % 2 == 1 {
-                text = format!("{}│ {}", " ".repeat(l), text);
-            } else {
-                text = format!(" {}", text);
-            }
-
-            res = format!("{}│     │{}{}│\n", res, text, " ".repeat(n));
+            let ending = self.finish();
+            let columns = (0..seats)
+                .map(|n| match ending.notation(n) {
+                    Some(text) => {
+                        let text = format!(" {}/{}", text, self.players[n].score);
+                        format!("{:<l$}", text, l = l)
+                    }
+                    None => " ".repeat(l),
+                })
+                .collect::<Vec<String>>();
+            res = format!("{}│     │{}│\n", res, columns.join("│"));
+            res = format!(
+                "{}│     │{:^w$}│\n",
+                res,
+                self.result(),
+                w = seats * (l + 1) - 1
+            );
         }
 
-        res = format!("{}└─────┴{}┴{}┘\n", res, "─".repeat(l), "─".repeat(l));
+        res = format!("{}{}", res, rule("└", "┴", "┘"));
 
         res
     }
 
-    pub fn to_str(&mut self) -> String {
+    // kept: what the last mover probably kept, marked in the tile tracker (see Game::infer)
+    pub fn to_str(&mut self, kept: Option<&Inference>) -> String {
         let board = format!("{}", self.board);
         let state = self.states_str();
         // let skills = self.skills_str();
-        let bag = self.board.bag.to_str_for_current_player(&self);
+        let bag = self.board.bag.to_str_for_current_player(&self, kept);
 
         // let mut rack = String::new();
         // if self.states() > 0 {
         //     rack = rack_to_string(self.get_rack(self.states() - 1), &self.board.bag);
         // }
 
-        let rack = rack_to_string(self.get_current_player().rack.clone(), &self.board.bag);
+        let rack = rack_to_string(
+            self.get_current_player().rack.clone(),
+            &self.board.bag,
+            self.board.rules.rack_size,
+        );
         // rack = format!("{} {} {}", rack, self.current, self.state);
         splice!(board, state, bag, rack)
     }
+
+    // the game so far as gcg (see gcg::write); mutable to score plays on the boards they were made on
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_gcg(&mut self) -> String {
+        gcg::write(self)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json(s: &str) -> Result<Game, String> {
+        serde_json::from_str(s).map_err(|e| e.to_string())
+    }
+
+    // as gcg for files ending in .gcg, otherwise as json
+    pub fn save(&mut self, path: &str) -> Result<(), String> {
+        let s = if path.ends_with(".gcg") {
+            self.to_gcg()
+        } else {
+            self.to_json()
+        };
+        fs::write(path, s).map_err(|e| format!("could not write {}: {}", path, e))
+    }
+
+    // json, or gcg for files ending in .gcg
+    pub fn load(path: &str) -> Result<Game, String> {
+        let s = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+        if path.ends_with(".gcg") {
+            return gcg::parse(&s);
+        }
+        Game::from_json(&s)
+    }
+
+    /*
+    The game to play: the one saved at load if given, which keeps its own players and rules,
+    or a new one for names with their strategies. A saved game keeps its clocks unless new ones are given.
+    */
+    pub fn open(
+        load: Option<&str>,
+        names: Vec<String>,
+        strategies: Vec<Option<Box<dyn Strategy>>>,
+        rules: Rules,
+        clock: Option<TimeControl>,
+    ) -> Result<Game, String> {
+        let mut game = match load {
+            Some(path) => Game::load(path)?,
+            None => {
+                let mut game = Game::with_players(names, rules);
+                game.set_strategies(strategies);
+                game
+            }
+        };
+        if clock.is_some() {
+            game.set_clock(clock);
+        }
+        Ok(game)
+    }
+}
+
+impl Serialize for Game {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        SavedGame {
+            version: SAVE_VERSION,
+            players: self.players.to_vec(),
+            board: &self.board,
+            current: self.current,
+            turn: self.turn,
+            finished: self.finished,
+            scoreless: self.scoreless,
+            history: self.history.clone(),
+            state: self.state,
+            adjustments: self.adjustments.clone(),
+            clock: self.clock.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Game, D::Error> {
+        let saved = SavedGame::<Board>::deserialize(deserializer)?;
+        if !(1..=SAVE_VERSION).contains(&saved.version) {
+            return Err(D::Error::custom(format!(
+                "unsupported save version {}",
+                saved.version
+            )));
+        }
+        if !(2..=MAX_PLAYERS).contains(&saved.players.len()) {
+            return Err(D::Error::custom(format!(
+                "a game needs 2 to {} players",
+                MAX_PLAYERS
+            )));
+        }
+        if saved.history.is_empty() || saved.state == 0 || saved.state > saved.history.len() {
+            return Err(D::Error::custom("history does not match the current state"));
+        }
+
+        let mut history = saved.history;
+        if saved.version == 1 {
+            // draws were not saved, but they are what each rack holds at its player's next move
+            let seats = saved.players.len();
+            for i in 1..history.len() {
+                let next = match history.get(i + seats) {
+                    Some(t) => t.rack.clone(),
+                    None => saved.players[(i - 1) % seats].rack.clone(),
+                };
+                history[i].drawn = without(next, &history[i].leave());
+            }
+        }
+
+        let finished = saved.finished;
+        Ok(Game {
+            players: saved.players,
+            board: saved.board,
+            current: saved.current,
+            turn: saved.turn,
+            finished: saved.finished,
+            scoreless: saved.scoreless,
+            history,
+            state: saved.state,
+            adjustments: saved.adjustments,
+            // the clocks run again once the game is loaded
+            clock: saved.clock.map(|mut c| {
+                if !finished {
+                    c.start();
+                }
+                c
+            }),
+            move_time: None,
+            since: Instant::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Position;
+
+    // the game as it stood after some move, sorted where order does not matter
+    struct Snapshot {
+        state: [[char; 15]; 15],
+        blanks: Vec<usize>,
+        bag: Vec<char>,
+        racks: Vec<Vec<char>>,
+        scores: Vec<i32>,
+    }
+
+    fn sorted<T: Ord + Clone>(v: &[T]) -> Vec<T> {
+        let mut v = v.to_vec();
+        v.sort();
+        v
+    }
+
+    fn squares(blanks: &[Position]) -> Vec<usize> {
+        sorted(&blanks.iter().map(|p| p.to_int()).collect::<Vec<usize>>())
+    }
+
+    fn snapshot(game: &Game) -> Snapshot {
+        let board = game.get_board();
+        let seats = 0..game.seats() as i32;
+        Snapshot {
+            state: board.get_board(),
+            blanks: squares(&board.blanks),
+            bag: sorted(&board.bag.distribution),
+            racks: seats
+                .clone()
+                .map(|n| sorted(&game.get_player(n).rack))
+                .collect(),
+            scores: seats.map(|n| game.get_player(n).score).collect(),
+        }
+    }
+
+    #[test]
+    fn skill_waits_until_asked_for() {
+        let mut game = Game::default();
+        let rack = game.get_current_player().rack.clone();
+        let moves = ranked(game.get_board_mut(), &rack, true).0;
+        let worst = moves.iter().rev().find(|m| m.typ == Type::Play).unwrap();
+        game.force_move(worst);
+        game.do_move(false);
+        assert_eq!(game.history[1].skill, None);
+
+        let expected = (moves[0].evaluation - worst.evaluation).abs();
+        assert_eq!(game.skill(1), expected);
+        assert_eq!(game.history[1].skill, Some(expected));
+        assert_eq!(game.skill(2), 0.0);
+    }
+
+    #[test]
+    fn positions_replay_the_game() {
+        let mut game = Game::default();
+        let mut snapshots = vec![snapshot(&game)];
+        for k in 1..=12 {
+            match k {
+                2 => {
+                    let rack = game.get_current_player().rack.clone();
+                    game.force_move(&Move::exchange(&rack[..3].to_vec()));
+                }
+                5 => game.force_move(&Move::pass()),
+                _ => {
+                    game.do_move(false);
+                }
+            }
+            snapshots.push(snapshot(&game));
+        }
+        assert_eq!(game.get_move(2).typ, Type::Exch);
+        assert_eq!(game.get_move(5).typ, Type::Pass);
+
+        let seats = game.seats();
+        let mut scores = vec![0; seats];
+        for (k, snap) in snapshots.iter().enumerate() {
+            let (state, blanks, _, bag, _) = game.position(k);
+            assert_eq!(state, snap.state, "board after {} moves", k);
+            assert_eq!(squares(&blanks), snap.blanks, "blanks after {} moves", k);
+            assert_eq!(sorted(&bag), snap.bag, "bag after {} moves", k);
+
+            if k > 0 {
+                scores[(k - 1) % seats] += game.get_move(k).score;
+            }
+            assert_eq!(scores, snap.scores, "scores after {} moves", k);
+            if k + 1 < game.states() {
+                let rack = &snap.racks[k % seats];
+                assert_eq!(
+                    &sorted(&game.get_rack(k + 1)),
+                    rack,
+                    "rack after {} moves",
+                    k
+                );
+            }
+        }
+    }
 }
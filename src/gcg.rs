@@ -0,0 +1,525 @@
+use crate::board::Rules;
+use crate::dictionary::LEXICON;
+use crate::game::{Adjustment, Game, MAX_PLAYERS};
+use crate::utils::{ItemRemovable, Move, Type};
+
+/*
+Reads a game written in gcg (https://www.poslarchive.com/www.scrabbleplayers.org/gcg.html)
+back into a Game, replaying every move so the whole history can be stepped through.
+
+The lines it understands:
+    #player1 nick Full Name        one per player, in seat order
+    >nick: RACK 8H WO.D +14 14     a play (parentheses work as well as dots, blanks are lowercase)
+    >nick: RACK -ABC +0 14         an exchange, or -3 if the tiles are not known
+    >nick: RACK - +0 14            a pass
+    >nick: RACK -- -14 0           the last play, challenged off
+    >nick: RACK (challenge) +5 19  a bonus for a play challenged without success
+    >nick: RACK (time) -10 9       a time penalty
+    >nick: (AEI) +6 420            going out, with the tiles left on the other racks
+    >nick: AEI (AEI) -3 380        tiles left on a rack at the end
+    #note Time: 1200               milliseconds the move above took (see write)
+Any other pragma (#note, #lexicon, ...) and the lines continuing a note are skipped.
+
+Draws are not recorded, so each rack is set to the tiles the line shows just before the move
+(see set_rack). Scores are taken from the file, which lets it keep phonies and other rules,
+but the running totals have to add up.
+*/
+
+// a line with its number (from 1), for errors
+type Line<'a> = (usize, &'a str);
+
+pub fn parse(s: &str) -> Result<Game, String> {
+    let lines = s
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .collect::<Vec<Line>>();
+
+    let mut names = lines
+        .iter()
+        .filter(|(_, l)| l.starts_with("#player"))
+        .map(|&(i, l)| match l.split_whitespace().nth(1) {
+            Some(nick) => Ok(nick.to_string()),
+            None => Err(format!("line {}: player without a name", i)),
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+    if names.len() < 2 || names.len() > MAX_PLAYERS {
+        return Err(format!(
+            "{} players, a game needs 2 to {}",
+            names.len(),
+            MAX_PLAYERS
+        ));
+    }
+
+    // the first to move sits first
+    if let Some(&(i, l)) = lines.iter().find(|(_, l)| l.starts_with('>')) {
+        let nick = nick(l).map_err(|e| format!("line {}: {}", i, e))?;
+        match names.iter().position(|n| *n == nick) {
+            Some(first) => names.rotate_left(first),
+            None => return Err(format!("line {}: {} is not a player", i, nick)),
+        }
+    }
+
+    let mut game = Game::with_players(names, Rules::default());
+    let mut ending = vec![]; // end of game lines, checked once the racks are settled
+
+    for &(i, l) in lines.iter() {
+        if let Some(ms) = l.strip_prefix("#note Time:") {
+            let ms = number(ms.trim()).map_err(|e| format!("line {}: {}", i, e))?;
+            let last = game.states() - 1;
+            game.set_time(last, Some(ms.max(0) as u64));
+            continue;
+        }
+        if !l.starts_with('>') {
+            continue;
+        }
+
+        let states = game.states();
+        let fields = l.split_whitespace().skip(1).collect::<Vec<&str>>();
+        if !is_end(&fields) && !ending.is_empty() && !game.finished {
+            settle(&mut game, &ending)?;
+        }
+
+        read_line(&mut game, i, l, &fields, &mut ending)
+            .map_err(|e| format!("line {}: {}", i, e))?;
+        // the time it took to read is not the time it took to play
+        if game.states() > states {
+            game.set_time(game.states() - 1, None);
+        }
+    }
+
+    if !ending.is_empty() && !game.finished {
+        settle(&mut game, &ending)?;
+    } else if game.is_over() {
+        game.finish();
+    }
+
+    Ok(game)
+}
+
+// the tiles left at the end of the game: "(AEI) +6 420" or "AEI (AEI) -3 380"
+fn is_end(fields: &[&str]) -> bool {
+    match fields {
+        [left, _, _] | [_, left, _, _] => {
+            left.starts_with('(') && *left != "(challenge)" && *left != "(time)"
+        }
+        _ => false,
+    }
+}
+
+fn nick(l: &str) -> Result<String, String> {
+    match l[1..].split_whitespace().next() {
+        Some(nick) if nick.ends_with(':') => Ok(nick.trim_end_matches(':').to_string()),
+        _ => Err(String::from("expected >nick:")),
+    }
+}
+
+fn number(s: &str) -> Result<i32, String> {
+    s.parse().map_err(|_| format!("{} is not a number", s))
+}
+
+fn tiles(s: &str) -> Result<Vec<char>, String> {
+    if s.chars().all(|c| c.is_ascii_uppercase() || c == '?') {
+        Ok(s.chars().collect())
+    } else {
+        Err(format!("{} is not a rack", s))
+    }
+}
+
+fn read_line(
+    game: &mut Game,
+    i: usize,
+    l: &str,
+    fields: &[&str],
+    ending: &mut Vec<(usize, usize, i32)>,
+) -> Result<(), String> {
+    let nick = nick(l)?;
+    let seat = match (0..game.seats()).find(|&n| game.get_player(n as i32).name == nick) {
+        Some(n) => n,
+        None => return Err(format!("{} is not a player", nick)),
+    };
+    let total = number(fields.last().unwrap_or(&""))?;
+
+    if is_end(fields) {
+        match fields {
+            // the other racks when going out; with more than two players their own lines tell them apart
+            [out, _, _] if game.seats() == 2 => {
+                let left = tiles(out.trim_matches(|c| c == '(' || c == ')'))?;
+                set_rack(game, 1 - seat, &left)?;
+            }
+            [rack, left, _, _] => {
+                if *left != format!("({})", rack) {
+                    return Err(format!("{} left, but {} on the rack", left, rack));
+                }
+                set_rack(game, seat, &tiles(rack)?)?;
+            }
+            _ => {}
+        }
+        ending.push((i, seat, total));
+        return Ok(());
+    }
+
+    match fields {
+        // the rack is left out by some programs, and is empty after going out
+        [.., "(challenge)", points, _] => game.adjust(seat, Adjustment::Challenge(number(points)?)),
+        [.., "(time)", points, _] => game.adjust(seat, Adjustment::Time(number(points)?)),
+        [_, "--", _, _] => {
+            if game.current != (seat + 1) % game.seats() {
+                return Err(format!("{} did not play last", nick));
+            }
+            game.withdraw();
+        }
+        [rack, m, _, _] if m.starts_with('-') => {
+            to_move(game, seat)?;
+            let rack = tiles(rack)?;
+            set_rack(game, seat, &rack)?;
+            let m = match *m {
+                "-" => Move::pass(),
+                m => match m[1..].parse::<usize>() {
+                    Ok(n) if n <= rack.len() => Move::exchange(&rack[..n].to_vec()),
+                    Ok(n) => return Err(format!("cannot exchange {} of {}", n, rack.len())),
+                    Err(_) => Move::exchange(&tiles(&m[1..])?),
+                },
+            };
+            if !game.get_player(seat as i32).has_tiles(&m.reals()) {
+                return Err(format!(
+                    "{} does not have {}",
+                    rack.iter().collect::<String>(),
+                    m.word()
+                ));
+            }
+            game.force_move(&m);
+        }
+        [rack, pos, word, score, _] => {
+            to_move(game, seat)?;
+            set_rack(game, seat, &tiles(rack)?)?;
+            let mut m = game.get_board().parse_play(pos, word)?;
+            if !game.get_player(seat as i32).has_tiles(&m.reals()) {
+                let tiles = m.reals().iter().collect::<String>();
+                return Err(format!("{} does not have {}", rack, tiles));
+            }
+            m.score = number(score)?;
+            game.force_move(&m);
+        }
+        _ => return Err(format!("cannot read {}", l)),
+    }
+
+    let score = game.get_player(seat as i32).score;
+    if score != total {
+        return Err(format!("{} has {}, not {}", nick, score, total));
+    }
+    Ok(())
+}
+
+fn to_move(game: &Game, seat: usize) -> Result<(), String> {
+    if game.finished || game.current != seat {
+        return Err(format!("not {}'s turn", game.get_player(seat as i32).name));
+    }
+    Ok(())
+}
+
+/*
+Gives player n the tiles of a recorded rack, before their move. The old rack goes back in the bag
+and the tiles come out of it; one that is only left on another rack is taken from there, and that
+player draws another. Racks recorded short are drawn up.
+*/
+fn set_rack(game: &mut Game, n: usize, rack: &[char]) -> Result<(), String> {
+    let mut bag = game.get_board().bag.distribution.clone();
+    bag.extend(game.get_player(n as i32).rack.iter());
+
+    let mut short = vec![];
+    for c in rack {
+        if bag.contains(c) {
+            bag._remove_item(*c);
+            continue;
+        }
+        let other = (0..game.seats())
+            .filter(|&o| o != n)
+            .find(|&o| game.get_player(o as i32).rack.contains(c));
+        match other {
+            Some(o) => {
+                game.get_player_mut(o as i32).rack._remove_item(*c);
+                short.push(o);
+            }
+            None => {
+                return Err(format!(
+                    "no {} left for {}",
+                    c,
+                    game.get_player(n as i32).name
+                ))
+            }
+        }
+    }
+
+    let size = game.get_board().rules.rack_size;
+    let board = game.get_board_mut();
+    board.bag.distribution = bag;
+    // the others had full racks, so they draw first
+    let replacements = board.bag.draw_tiles(short.len());
+    let mut new = rack.to_vec();
+    if new.len() < size {
+        new.extend(board.bag.draw_tiles(size - new.len()));
+    }
+    game.get_player_mut(n as i32).rack = new;
+    for (o, c) in short.into_iter().zip(replacements) {
+        game.get_player_mut(o as i32).rack.push(c);
+    }
+
+    Ok(())
+}
+
+// settles the racks (see Game::finish) and checks the totals on the end of game lines
+fn settle(game: &mut Game, ending: &[(usize, usize, i32)]) -> Result<(), String> {
+    game.finish();
+    for &(i, n, total) in ending {
+        let p = game.get_player(n as i32);
+        if p.score != total {
+            return Err(format!(
+                "line {}: {} has {}, not {}",
+                i, p.name, p.score, total
+            ));
+        }
+    }
+    Ok(())
+}
+
+/*
+Writes a game as gcg, in the form parse reads: a header, a line for every move (a play challenged off
+is the play followed by a "--" line) with notes of the time it took, if known, and of how a play
+scored, challenge bonuses after the plays they were for, and once the game is finished the racks
+left at the end, then any time penalties. Takes the game mutably to score plays on their boards.
+*/
+pub fn write(game: &mut Game) -> String {
+    let seats = game.seats();
+    let names = (0..seats)
+        .map(|n| game.get_player(n as i32).name.clone())
+        .collect::<Vec<String>>();
+
+    let mut res = String::from("#character-encoding UTF-8");
+    for (n, name) in names.iter().enumerate() {
+        res = format!("{}\n#player{} {} {}", res, n + 1, name, name);
+    }
+    let players = (0..seats)
+        .map(|n| {
+            let p = game.get_player(n as i32);
+            match &p.strategy {
+                Some(s) => format!("{}: {}", p.name, s.name()),
+                None => format!("{}: human", p.name),
+            }
+        })
+        .collect::<Vec<String>>();
+    res = format!(
+        "{}\n#title {}\n#description {}\n#lexicon {}",
+        res,
+        names.join(" vs "),
+        players.join(", "),
+        LEXICON
+    );
+
+    let mut totals = vec![0; seats];
+    let mut last_rack = vec![String::new(); seats];
+    for n in 1..game.states() {
+        let seat = (n - 1) % seats;
+        let m = game.get_move(n);
+        let rack = game.get_rack(n).iter().collect::<String>();
+        let line = format!(">{}: {}", names[seat], rack);
+
+        // a play challenged off is a pass in the history
+        let withdrawn = game.adjustments().iter().find_map(|(after, _, a)| match a {
+            Adjustment::Withdrawn(w) if *after == n => Some(w),
+            _ => None,
+        });
+        match withdrawn {
+            Some(w) => {
+                let total = totals[seat] + w.score;
+                res = format!(
+                    "{}\n{} {} +{} {}",
+                    res,
+                    line,
+                    w.notation(false),
+                    w.score,
+                    total
+                );
+                res = format!("{}\n{} -- -{} {}", res, line, w.score, totals[seat]);
+            }
+            None => {
+                totals[seat] += m.score;
+                res = format!(
+                    "{}\n{} {} {:+} {}",
+                    res,
+                    line,
+                    m.notation(false),
+                    m.score,
+                    totals[seat]
+                );
+            }
+        }
+        last_rack[seat] = rack;
+
+        if let Some(time) = game.get_time(n) {
+            res = format!("{}\n#note Time: {}", res, time);
+        }
+        if m.typ == Type::Play {
+            res = format!("{}\n#note Score: {}", res, game.breakdown(n).to_str());
+        }
+
+        for (_, p, a) in game
+            .adjustments()
+            .iter()
+            .filter(|(after, _, _)| *after == n)
+        {
+            if let Adjustment::Challenge(points) = a {
+                totals[*p] += points;
+                res = format!(
+                    "{}\n>{}: {} (challenge) {:+} {}",
+                    res, names[*p], last_rack[*p], points, totals[*p]
+                );
+            }
+        }
+    }
+
+    if game.finished {
+        let ending = game.ending();
+        for n in 0..seats {
+            totals[n] += ending.changes[n];
+            if let Some(line) = ending.to_gcg(n, &names[n], totals[n]) {
+                res = format!("{}\n{}", res, line);
+            }
+        }
+    }
+
+    for (_, p, a) in game.adjustments().iter() {
+        if let Adjustment::Time(points) = a {
+            totals[*p] += points;
+            let rack = game.get_player(*p as i32).rack.iter().collect::<String>();
+            // no rack after going out
+            let line = if rack.is_empty() {
+                format!(">{}: (time)", names[*p])
+            } else {
+                format!(">{}: {} (time)", names[*p], rack)
+            };
+            res = format!("{}\n{} {:+} {}", res, line, points, totals[*p]);
+        }
+    }
+
+    format!("{}\n", res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // writes game, reads it back and checks it comes out the same
+    fn round_trip(game: &mut Game) -> Game {
+        let text = write(game);
+        let mut back = parse(&text).unwrap_or_else(|e| panic!("{}\n{}", e, text));
+
+        assert_eq!(write(&mut back), text);
+        assert_eq!(back.states(), game.states());
+        assert_eq!(back.finished, game.finished);
+        for n in 0..game.seats() {
+            assert_eq!(
+                back.get_player(n as i32).score,
+                game.get_player(n as i32).score
+            );
+        }
+        back
+    }
+
+    fn exchange(game: &mut Game, n: usize) {
+        let rack = game.get_current_player().rack.clone();
+        game.force_move(&Move::exchange(&rack[..n].to_vec()));
+    }
+
+    #[test]
+    fn passes_exchanges_and_adjustments() {
+        let mut game = Game::default();
+        game.do_move(false);
+        // challenged off, then the other player exchanges
+        game.withdraw();
+        exchange(&mut game, 3);
+        game.do_move(false);
+        // an unsuccessful challenge, then a pass
+        game.adjust(0, Adjustment::Challenge(5));
+        game.force_move(&Move::pass());
+        game.do_move(false);
+        game.adjust(1, Adjustment::Time(-10));
+
+        let text = write(&mut game);
+        for part in [
+            " -- ",
+            " (challenge) +5 ",
+            " (time) -10 ",
+            " - +0 ",
+            "#note Time: ",
+        ]
+        .iter()
+        {
+            assert!(text.contains(part), "no {} in\n{}", part, text);
+        }
+        let back = round_trip(&mut game);
+        assert_eq!(back.get_move(2).typ, Type::Exch);
+        assert_eq!(back.get_move(4).typ, Type::Pass);
+    }
+
+    #[test]
+    fn going_out() {
+        // a static equity game nearly always ends with someone going out
+        for _ in 0..10 {
+            let mut game = Game::default();
+            while !game.is_over() {
+                game.do_move(false);
+            }
+            let ending = game.finish();
+            if let Some(out) = ending.out {
+                let back = round_trip(&mut game);
+                assert_eq!(back.ending().out, Some(out));
+                return;
+            }
+        }
+        panic!("nobody went out in 10 games");
+    }
+
+    #[test]
+    fn six_zeros() {
+        let mut game = Game::default();
+        game.do_move(false);
+        for turn in 0..6 {
+            if turn % 2 == 0 {
+                game.force_move(&Move::pass());
+            } else {
+                exchange(&mut game, 1);
+            }
+        }
+        assert!(game.is_over());
+        let ending = game.finish();
+        assert_eq!(ending.out, None);
+
+        let back = round_trip(&mut game);
+        assert_eq!(back.ending().racks, ending.racks);
+    }
+
+    #[test]
+    fn times_come_from_the_history() {
+        let mut game = Game::default();
+        for _ in 0..4 {
+            game.do_move(false);
+        }
+        game.set_time(1, Some(61_250));
+        game.set_time(2, None);
+        game.set_time(3, Some(0));
+        game.set_time(4, Some(7));
+
+        let text = write(&mut game);
+        let times = text
+            .lines()
+            .filter_map(|l| l.strip_prefix("#note Time: "))
+            .collect::<Vec<&str>>();
+        assert_eq!(times, vec!["61250", "0", "7"]);
+
+        let back = round_trip(&mut game);
+        for n in 1..=4 {
+            assert_eq!(back.get_time(n), game.get_time(n));
+        }
+    }
+}
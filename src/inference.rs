@@ -0,0 +1,148 @@
+use crate::board::Board;
+use crate::strategy::{ranked, Budget};
+use crate::utils::{ItemRemovable, Move, Type, ALPH};
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+// how sharply a static-equity player prefers its best move: 1 point of equity is worth e^BETA
+const BETA: f32 = 0.3;
+// leaves tried per inference
+pub const SAMPLES: usize = 50;
+
+/*
+What the opponent probably kept after their last play.
+
+Possible leaves are drawn from the unseen tiles, and each is weighted by how likely a
+static-equity player holding it plus the played tiles would have made that play
+(a softmax over the equity of all its moves). The weights sum to 1.
+*/
+#[derive(Clone)]
+pub struct Inference {
+    pub leaves: Vec<(Vec<char>, f32)>,
+}
+
+impl Inference {
+    /*
+    Draws an opponent rack of n tiles from pool: a kept leave picked by weight, filled up at random.
+    Returns: the rack and the rest of the pool (the bag).
+    */
+    pub fn sample(&self, pool: &[char], n: usize) -> (Vec<char>, Vec<char>) {
+        let mut rng = thread_rng();
+        let mut rest = pool.to_vec();
+        rest.shuffle(&mut rng);
+
+        let mut rack = Vec::new();
+        if let Ok(dist) = WeightedIndex::new(self.leaves.iter().map(|(_, w)| *w)) {
+            let leave = &self.leaves[dist.sample(&mut rng)].0;
+            // the pool may have changed since; only take what is still unseen
+            for c in leave {
+                if rack.len() < n && rest.contains(c) {
+                    rest._remove_item(*c);
+                    rack.push(*c);
+                }
+            }
+        }
+
+        let k = (n - rack.len()).min(rest.len());
+        rack.extend(rest.split_off(rest.len() - k));
+        (rack, rest)
+    }
+
+    /*
+    Returns: for each letter, how many of it the opponent is expected to have kept.
+    */
+    pub fn holding(&self) -> Vec<(char, f32)> {
+        ALPH.chars()
+            .map(|c| {
+                let n = self
+                    .leaves
+                    .iter()
+                    .map(|(l, w)| l.iter().filter(|&&x| x == c).count() as f32 * w)
+                    .sum::<f32>();
+                (c, n)
+            })
+            .filter(|(_, n)| *n > 0.0)
+            .collect()
+    }
+
+    pub fn to_str(&self) -> String {
+        let mut holding = self.holding();
+        holding.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        holding
+            .iter()
+            .take(7)
+            .map(|(c, n)| format!("{} {:.1}", c, n))
+            .collect::<Vec<String>>()
+            .join("  ")
+    }
+}
+
+/*
+Infers the leave behind m, a play made on `before` (the board as it was before the play).
+pool is every tile unseen by whoever is asking, samples how many leaves to try
+(fewer if the budget runs out; a node is one sample).
+Only plays say anything about the leave, so exchanges and passes give None.
+*/
+pub fn infer(
+    before: &mut Board,
+    m: &Move,
+    pool: &[char],
+    samples: usize,
+    budget: Budget,
+) -> Option<Inference> {
+    if m.typ != Type::Play {
+        return None;
+    }
+
+    let played = m
+        .reals()
+        .iter()
+        .map(|c| if c.is_lowercase() { '?' } else { *c })
+        .collect::<Vec<char>>();
+    let size = before.rules.rack_size.saturating_sub(played.len());
+
+    let mut rng = thread_rng();
+    let mut leaves = Vec::new();
+    let mut total = 0.0;
+    for i in 0..samples {
+        if budget.spent(i) {
+            break;
+        }
+
+        let mut leave = pool
+            .choose_multiple(&mut rng, size)
+            .cloned()
+            .collect::<Vec<char>>();
+        leave.sort();
+
+        let mut rack = leave.clone();
+        rack.extend(played.iter());
+
+        let (moves, eval_val) = ranked(before, &rack, false);
+        let eq = |x: &Move| x.eval(1.0, eval_val);
+        let w = match (moves.first(), moves.iter().find(|x| *x == m)) {
+            (Some(best), Some(found)) => {
+                let z = moves
+                    .iter()
+                    .map(|x| (BETA * (eq(x) - eq(best))).exp())
+                    .sum::<f32>();
+                (BETA * (eq(found) - eq(best))).exp() / z
+            }
+            _ => 0.0, // could not have been played from this rack
+        };
+
+        total += w;
+        leaves.push((leave, w));
+    }
+
+    if total <= 0.0 {
+        return None;
+    }
+    for (_, w) in leaves.iter_mut() {
+        *w /= total;
+    }
+
+    Some(Inference { leaves })
+}
@@ -0,0 +1,430 @@
+use crate::board::{Board, Rules};
+use crate::player::Player;
+use crate::strategy::ranked;
+use crate::utils::{to_word, Move, Type, ALPH};
+
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+
+/*
+Heuristic leave values, for leaves the superleave table does not have
+(custom lexicons and tile sets, or no resources/leaves.txt at all).
+
+Leaves are counts per letter of ALPH (see utils::to_word), like the table's keys.
+The value is built from what each tile is worth to keep on its own, with penalties for
+duplicates, for too many vowels or consonants, and for a Q without a U, and a bonus
+for holding a blank and an S together. The weights below are in points of a full-size
+tile set, and SCALE brings them down to roughly the size of the table's values.
+*/
+
+const SCALE: f32 = 0.28;
+
+// worth of keeping a single tile, in ALPH order (the blank last)
+const SINGLE: [f32; 27] = [
+    1.0, -1.5, 0.5, 0.5, 1.5, -1.5, -1.5, 1.0, -0.5, -1.5, -0.5, -0.5, 0.5, 0.5, -1.0, -0.5, -5.0,
+    1.5, 8.0, 0.5, -3.0, -4.5, -2.5, 3.5, -0.5, 3.0, 25.0,
+];
+
+const DUPLICATE: f32 = -3.0; // for each copy of a letter after the first
+const BALANCE: f32 = -1.5; // times the square of how far the vowel count is from ideal
+const VOWEL_SHARE: f32 = 0.4;
+const Q_WITHOUT_U: f32 = -4.0;
+const BLANK_AND_S: f32 = 2.5;
+
+fn count(leave: &[usize], c: char) -> usize {
+    ALPH.find(c).map(|i| leave[i]).unwrap_or(0)
+}
+
+pub fn heuristic(leave: &[usize]) -> f32 {
+    let mut value = 0.0;
+    for (i, &n) in leave.iter().enumerate() {
+        if n > 0 {
+            value += SINGLE[i] * n as f32 + DUPLICATE * (n - 1) as f32;
+        }
+    }
+
+    // blanks can be either, so they are left out of the balance
+    let vowels = "AEIOU".chars().map(|c| count(leave, c)).sum::<usize>() as f32;
+    let letters = (leave.iter().sum::<usize>() - count(leave, '?')) as f32;
+    value += BALANCE * (vowels - VOWEL_SHARE * letters).powi(2);
+
+    if count(leave, 'Q') > 0 && count(leave, 'U') == 0 {
+        value += Q_WITHOUT_U;
+    }
+    if count(leave, '?') > 0 && count(leave, 'S') > 0 {
+        value += BLANK_AND_S;
+    }
+
+    value * SCALE
+}
+
+/*
+Leave files, in three formats:
+    text         one "LEAVE value" line per leave (resources/leaves.txt), for paths ending in .txt
+    superleaves  the packed binary other programs ship: for each leave a length byte, its letters
+                 (1 for the blank, 5 to 30 for A to Z) and a two-byte little-endian value,
+                 in 256ths of a point offset by 128
+    compact      our own: MAGIC, then leaves as sorted alphagrams in ALPH order (blank last), each a byte
+                 for the prefix shared with the leave before, a byte for how many letters follow,
+                 the letters as ALPH indices, and the value as a little-endian i16 in hundredths
+Reading tells compact files by MAGIC and text by extension; anything else is read as superleaves.
+Writing uses text for .txt paths and compact otherwise.
+*/
+
+const MAGIC: &[u8] = b"GLV1";
+
+pub type Table = HashMap<Vec<usize>, f32>;
+
+fn leave_str(leave: &[usize]) -> String {
+    ALPH.chars()
+        .zip(leave.iter())
+        .map(|(c, &n)| c.to_string().repeat(n))
+        .collect()
+}
+
+fn read_text(s: &str) -> Result<Table, String> {
+    s.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut parts = line.split_whitespace();
+            let leave = parts.next().unwrap_or("");
+            match parts.next().map(|v| v.parse::<f32>()) {
+                Some(Ok(v)) if leave.chars().all(|c| ALPH.contains(c)) => {
+                    Ok((to_word(&leave.chars().collect()), v))
+                }
+                _ => Err(format!("bad leave line: {}", line)),
+            }
+        })
+        .collect()
+}
+
+fn read_superleaves(b: &[u8]) -> Result<Table, String> {
+    let mut table = HashMap::new();
+    let mut i = 0;
+    while i < b.len() {
+        let n = b[i] as usize;
+        if i + 1 + n + 2 > b.len() {
+            return Err(format!("superleaves file cut off at byte {}", i));
+        }
+
+        let mut leave = Vec::new();
+        for &l in &b[i + 1..i + 1 + n] {
+            match l {
+                1 => leave.push('?'),
+                5..=30 => leave.push((b'A' + l - 5) as char),
+                _ => return Err(format!("bad letter {} at byte {}", l, i)),
+            }
+        }
+        let raw = u16::from_le_bytes([b[i + 1 + n], b[i + 2 + n]]);
+        table.insert(to_word(&leave), raw as f32 / 256.0 - 128.0);
+
+        i += n + 3;
+    }
+
+    Ok(table)
+}
+
+fn read_compact(b: &[u8]) -> Result<Table, String> {
+    let mut table = HashMap::new();
+    let mut last: Vec<u8> = Vec::new();
+    let mut i = MAGIC.len();
+    while i < b.len() {
+        let (shared, n) = (b[i] as usize, *b.get(i + 1).unwrap_or(&0) as usize);
+        if shared > last.len() || i + 2 + n + 2 > b.len() {
+            return Err(format!("compact leaves file corrupt at byte {}", i));
+        }
+
+        last.truncate(shared);
+        last.extend_from_slice(&b[i + 2..i + 2 + n]);
+        let mut leave = vec![0; ALPH.len()];
+        for &l in &last {
+            match leave.get_mut(l as usize) {
+                Some(c) => *c += 1,
+                None => return Err(format!("bad letter {} at byte {}", l, i)),
+            }
+        }
+        let raw = i16::from_le_bytes([b[i + 2 + n], b[i + 3 + n]]);
+        table.insert(leave, raw as f32 / 100.0);
+
+        i += n + 4;
+    }
+
+    Ok(table)
+}
+
+fn write_compact(table: &Table) -> Vec<u8> {
+    // alphagrams as ALPH indices; sorting them puts shared prefixes next to each other
+    let mut leaves = table
+        .iter()
+        .map(|(leave, v)| {
+            let letters = leave
+                .iter()
+                .enumerate()
+                .flat_map(|(i, &n)| vec![i as u8; n])
+                .collect::<Vec<u8>>();
+            (letters, *v)
+        })
+        .collect::<Vec<(Vec<u8>, f32)>>();
+    leaves.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut b = MAGIC.to_vec();
+    let mut last: &[u8] = &[];
+    for (letters, v) in leaves.iter() {
+        let shared = last
+            .iter()
+            .zip(letters.iter())
+            .take_while(|(x, y)| x == y)
+            .count();
+        b.push(shared as u8);
+        b.push((letters.len() - shared) as u8);
+        b.extend_from_slice(&letters[shared..]);
+        let raw = (v * 100.0)
+            .round()
+            .max(i16::MIN as f32)
+            .min(i16::MAX as f32) as i16;
+        b.extend_from_slice(&raw.to_le_bytes());
+        last = letters;
+    }
+
+    b
+}
+
+pub fn read(path: &str) -> Result<Table, String> {
+    let b = fs::read(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    if b.starts_with(MAGIC) {
+        read_compact(&b)
+    } else if path.ends_with(".txt") {
+        read_text(&String::from_utf8_lossy(&b))
+    } else {
+        read_superleaves(&b)
+    }
+}
+
+pub fn write(table: &Table, path: &str) -> Result<(), String> {
+    let b = if path.ends_with(".txt") {
+        // the empty leave has no letters to write, and is worth 0 anyway
+        let mut lines = table
+            .iter()
+            .filter(|(leave, _)| leave.iter().any(|&n| n > 0))
+            .map(|(leave, v)| format!("{} {:.3}\n", leave_str(leave), v))
+            .collect::<Vec<String>>();
+        lines.sort();
+        lines.concat().into_bytes()
+    } else {
+        write_compact(table)
+    };
+
+    fs::write(path, b).map_err(|e| format!("could not write {}: {}", path, e))
+}
+
+// converts a leaves file between formats
+pub fn convert(from: &str, to: &str) {
+    match read(from).and_then(|table| write(&table, to).map(|_| table.len())) {
+        Ok(n) => println!("wrote {} leaves to {}", n, to),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/*
+Training leave values by self-play (the train-leaves command).
+
+Each round plays games between two static-equity players valuing leaves by the current table.
+Every leave kept while there are tiles to draw is credited with what its player scores
+over their next `horizon` turns, less the average over all such stretches (leaves kept
+too near the end to see them out are not counted). A leave's new value blends
+its mean over the round with its old one, weighted as PRIOR games' worth of samples,
+so rarely seen leaves move slowly. Rounds go on until the mean change falls below
+tolerance. Leaves never kept are left to the heuristic.
+*/
+
+const PRIOR: f32 = 10.0;
+const MAX_TURNS: usize = 100;
+
+// (leave, points scored over the next horizon turns) for every leave kept in one game
+fn self_play(board: &mut Board, horizon: usize) -> Vec<(Vec<usize>, i32)> {
+    board.reset();
+    let n = board.rules.rack_size;
+    let mut players = [
+        Player::new(String::new(), board.bag.draw_tiles(n), None),
+        Player::new(String::new(), board.bag.draw_tiles(n), None),
+    ];
+
+    // the leaves each player kept, with the points since and the turns still to wait
+    let mut pending: [Vec<(Vec<usize>, i32, usize)>; 2] = [Vec::new(), Vec::new()];
+    let mut samples = Vec::new();
+    let mut scoreless = 0;
+    for turn in 0..MAX_TURNS {
+        let side = turn % 2;
+        let drawing = !board.bag.distribution.is_empty();
+        let m = ranked(board, &players[side].rack, false)
+            .0
+            .into_iter()
+            .next()
+            .unwrap_or_else(Move::pass);
+
+        for (leave, score, left) in pending[side].iter_mut() {
+            *score += m.score;
+            *left -= 1;
+            if *left == 0 {
+                samples.push((leave.clone(), *score));
+            }
+        }
+        pending[side].retain(|p| p.2 > 0);
+        let leave = players[side].leave(m.reals());
+        if drawing && m.typ != Type::Pass && !leave.is_empty() {
+            pending[side].push((to_word(&leave), 0, horizon));
+        }
+
+        players[side].play(board, &m);
+        scoreless = if m.score == 0 { scoreless + 1 } else { 0 };
+        let racks = [players[0].rack.as_slice(), players[1].rack.as_slice()];
+        if board.went_out(&racks).is_some() || scoreless >= Board::scoreless_limit(2) {
+            break;
+        }
+    }
+
+    samples
+}
+
+/*
+Trains leave values for rules with `games` self-play games a round, for at most `rounds` rounds,
+crediting leaves over `horizon` turns, and writes them to out (see write).
+*/
+pub fn train(rules: Rules, games: usize, rounds: usize, horizon: usize, tolerance: f32, out: &str) {
+    let threads = rayon::current_num_threads();
+    let mut boards = (0..threads)
+        .map(|_| {
+            let mut board = Board::default();
+            board.rules = rules;
+            board
+        })
+        .collect::<Vec<Board>>();
+
+    // starts from nothing, so the heuristic plays the first round
+    let mut table: Table = HashMap::new();
+    for round in 1..=rounds {
+        let samples = boards
+            .par_iter_mut()
+            .enumerate()
+            .map(|(i, board)| {
+                board.set_leaves(table.clone());
+                // the first few threads play one game more, to make up games
+                let share = games / threads + if i < games % threads { 1 } else { 0 };
+                (0..share)
+                    .flat_map(|_| self_play(board, horizon))
+                    .collect::<Vec<(Vec<usize>, i32)>>()
+            })
+            .flatten()
+            .collect::<Vec<(Vec<usize>, i32)>>();
+
+        let average =
+            samples.iter().map(|(_, s)| *s as f32).sum::<f32>() / samples.len().max(1) as f32;
+        let mut sums: HashMap<Vec<usize>, (f32, f32)> = HashMap::new();
+        for (leave, score) in samples.iter() {
+            let e = sums.entry(leave.clone()).or_insert((0.0, 0.0));
+            e.0 += *score as f32 - average;
+            e.1 += 1.0;
+        }
+
+        let mut change = 0.0;
+        for (leave, (sum, count)) in sums.iter() {
+            let old = *table.get(leave).unwrap_or(&heuristic(leave));
+            let new = (sum + PRIOR * old) / (count + PRIOR);
+            change += (new - old).abs();
+            table.insert(leave.clone(), new);
+        }
+        change /= sums.len().max(1) as f32;
+
+        println!(
+            "round {}: {} leaves seen, {} valued, average over {} turns {:.2}, mean change {:.3}",
+            round,
+            sums.len(),
+            table.len(),
+            horizon,
+            average,
+            change
+        );
+        if change < tolerance {
+            break;
+        }
+    }
+
+    match write(&table, out) {
+        Ok(_) => println!("wrote {} leaves to {}", table.len(), out),
+        Err(e) => println!("{}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leave(s: &str) -> Vec<usize> {
+        to_word(&s.chars().collect())
+    }
+
+    fn table() -> Table {
+        [
+            ("A", 1.25),
+            ("AA", -3.5),
+            ("AEINST", 30.0),
+            ("AEINS?", 41.75),
+            ("QU", -0.5),
+            ("Q", -7.25),
+            ("?", 25.0),
+            ("??", 12.0),
+            ("VVW", -15.5),
+        ]
+        .iter()
+        .map(|(l, v)| (leave(l), *v))
+        .collect()
+    }
+
+    fn round_trip(name: &str) {
+        let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+        let path = path.to_str().unwrap();
+        write(&table(), path).unwrap();
+        let read_back = read(path);
+        fs::remove_file(path).unwrap();
+        assert_eq!(read_back.unwrap(), table());
+    }
+
+    #[test]
+    fn text_round_trips() {
+        round_trip("leaves.txt");
+    }
+
+    #[test]
+    fn compact_round_trips() {
+        round_trip("leaves.glv");
+    }
+
+    #[test]
+    fn superleaves_records() {
+        let b = [
+            1, 5, 0x40, 0x80, // A, 128.25 - 128
+            2, 1, 30, 0x00, 0x7d, // ?Z, 125 - 128
+            3, 21, 25, 21, 0x80, 0x81, // QUQ, 129.5 - 128
+            0, 0x00, 0x80, // the empty leave, 0
+        ];
+        let table = read_superleaves(&b).unwrap();
+
+        assert_eq!(table.len(), 4);
+        assert_eq!(table[&leave("A")], 0.25);
+        assert_eq!(table[&leave("Z?")], -3.0);
+        assert_eq!(table[&leave("QQU")], 1.5);
+        assert_eq!(table[&leave("")], 0.0);
+    }
+
+    #[test]
+    fn bad_superleaves() {
+        // cut off in the value, and a letter code out of range
+        assert!(read_superleaves(&[2, 5, 6, 0x00]).is_err());
+        assert!(read_superleaves(&[1, 31, 0x00, 0x80]).is_err());
+        assert!(read_superleaves(&[1, 2, 0x00, 0x80]).is_err());
+    }
+}
@@ -9,31 +9,181 @@ extern crate clap;
 extern crate gdk;
 extern crate termion;
 
-use clap::App;
+use board::Rules;
+use clap::{App, ArgMatches};
+use clock::TimeControl;
+use std::time::Duration;
+use strategy::Strategy;
 
 mod bag;
 mod board;
+mod clock;
+mod defense;
 mod dictionary;
+mod difficulty;
+mod endgame;
+mod equity;
 mod game;
+mod gcg;
+mod inference;
+mod leaves;
 mod play;
 mod player;
+mod preendgame;
 mod puzzle;
 mod simulate;
+mod strategy;
 mod text;
 #[macro_use]
 mod utils;
 mod viz;
 
+fn rules(matches: &ArgMatches) -> Rules {
+    let rack_size = matches.value_of("rack_size").unwrap();
+    let bingo = matches.value_of("bingo").unwrap();
+    // a rack has to fit on a row of the board
+    let rack_size = match rack_size.parse::<usize>() {
+        Ok(n) if (1..=15).contains(&n) => n,
+        _ => clap::Error::value_validation_auto(format!(
+            "--rack-size {} is not a number of tiles from 1 to 15",
+            rack_size
+        ))
+        .exit(),
+    };
+    let bingo = match bingo.parse::<i32>() {
+        Ok(b) => b,
+        Err(_) => clap::Error::value_validation_auto(format!(
+            "--bingo {} is not a number of points",
+            bingo
+        ))
+        .exit(),
+    };
+
+    Rules { rack_size, bingo }
+}
+
+fn move_time(matches: &ArgMatches) -> Option<Duration> {
+    let t = matches.value_of("time")?;
+    match t.parse::<f32>() {
+        Ok(s) if s.is_finite() && s > 0.0 => Some(Duration::from_secs_f32(s)),
+        _ => {
+            eprintln!("bad move time {} (seconds, more than 0)", t);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn clock(matches: &ArgMatches) -> Option<TimeControl> {
+    match matches.value_of("clock").map(TimeControl::parse) {
+        Some(Ok(c)) => Some(c),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        None => None,
+    }
+}
+
+// first and second always have a value, third and fourth are optional
+const SEATS: [&str; 4] = ["first", "second", "third", "fourth"];
+
+fn strategy(matches: &ArgMatches, seat: &str) -> Option<Box<dyn Strategy>> {
+    match strategy::from_name(matches.value_of(seat).unwrap()) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// the strategies of the players given on the command line, in seat order
+fn strategies(matches: &ArgMatches) -> Vec<Option<Box<dyn Strategy>>> {
+    SEATS
+        .iter()
+        .filter(|seat| matches.value_of(seat).is_some())
+        .map(|seat| strategy(matches, seat))
+        .collect()
+}
+
 fn main() {
     let yaml = load_yaml!("../cmd.yml");
     let matches = App::from(yaml).get_matches();
 
+    if let Some(path) = matches.value_of("leaves") {
+        if let Err(e) = dictionary::use_leaves(path) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
     if let Some(ref matches) = matches.subcommand_matches("text") {
-        text::main(matches.value_of("number").unwrap().parse::<u32>().unwrap());
-    } else if let Some(ref _matches) = matches.subcommand_matches("viz") {
-        viz::main();
+        text::main(
+            matches.value_of("number").unwrap().parse::<u32>().unwrap(),
+            rules(matches),
+            matches.value_of("json"),
+            strategies(matches),
+            move_time(matches),
+            clock(matches),
+        );
+    } else if let Some(ref matches) = matches.subcommand_matches("viz") {
+        viz::main(
+            matches.value_of("load").map(|s| s.to_string()),
+            strategies(matches),
+            clock(matches),
+        );
     } else if let Some(ref matches) = matches.subcommand_matches("simulate") {
-        simulate::main(matches.value_of("rack").unwrap().to_string());
+        simulate::main(
+            matches.value_of("rack").unwrap().to_string(),
+            matches
+                .value_of("candidates")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(),
+            matches.value_of("plies").unwrap().parse::<usize>().unwrap(),
+            matches
+                .value_of("iterations")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(),
+            matches.value_of("spread").unwrap().parse::<i32>().unwrap(),
+            matches.value_of("depth").unwrap().parse::<usize>().unwrap(),
+            matches.value_of("time").unwrap().parse::<u64>().unwrap(),
+        );
+    } else if let Some(ref matches) = matches.subcommand_matches("endgame") {
+        endgame::main(
+            matches.value_of("rack").unwrap().to_string(),
+            matches.value_of("opponent").map(|s| s.to_string()),
+            matches.value_of("depth").unwrap().parse::<usize>().unwrap(),
+            matches.value_of("time").unwrap().parse::<u64>().unwrap(),
+        );
+    } else if let Some(ref matches) = matches.subcommand_matches("train-leaves") {
+        leaves::train(
+            rules(matches),
+            matches.value_of("games").unwrap().parse::<usize>().unwrap(),
+            matches
+                .value_of("rounds")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(),
+            matches
+                .value_of("horizon")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap()
+                .max(1),
+            matches
+                .value_of("tolerance")
+                .unwrap()
+                .parse::<f32>()
+                .unwrap(),
+            matches.value_of("out").unwrap(),
+        );
+    } else if let Some(ref matches) = matches.subcommand_matches("convert-leaves") {
+        leaves::convert(
+            matches.value_of("from").unwrap(),
+            matches.value_of("to").unwrap(),
+        );
     } else if let Some(ref matches) = matches.subcommand_matches("puzzle") {
         puzzle::main(
             matches.value_of("turns").unwrap().parse::<u32>().unwrap(),
@@ -45,8 +195,17 @@ fn main() {
         );
     } else if let Some(ref matches) = matches.subcommand_matches("play") {
         play::main(
-            matches.value_of("first").unwrap().to_string(),
-            matches.value_of("second").unwrap().to_string(),
+            SEATS
+                .iter()
+                .filter_map(|seat| matches.value_of(seat))
+                .map(|name| name.to_string())
+                .collect(),
+            strategies(matches),
+            rules(matches),
+            matches.value_of("load"),
+            matches.value_of("save").unwrap(),
+            move_time(matches),
+            clock(matches),
         );
     }
 }
@@ -1,8 +1,13 @@
+use crate::board::Rules;
+use crate::clock::{Clock, TimeControl};
 use crate::game::Game;
+use crate::inference::{self, Inference};
+use crate::strategy::Strategy;
 use crate::utils::{self, Direction, ItemRemovable, Move, Position, RESET};
 
-use itertools::Itertools;
-use std::io::{self, stdin, Stdout, Write};
+use std::io::{self, Stdout, Write};
+use std::thread;
+use std::time::Duration;
 use termion::color;
 use termion::cursor;
 use termion::event::*;
@@ -23,6 +28,10 @@ pub struct TermionGame<'a> {
     valid: bool,
     type_pos: Option<Position>,
     exch: Vec<usize>,
+
+    command: Option<String>, // move typed in notation, after pressing ':'
+    message: String,
+    kept: Option<Inference>, // what the opponent probably kept after their last play
 }
 
 impl<'a> TermionGame<'a> {
@@ -38,6 +47,10 @@ impl<'a> TermionGame<'a> {
             valid: false,
             type_pos: None,
             exch: vec![],
+
+            command: None,
+            message: String::new(),
+            kept: None,
         };
 
         tg.set_rack();
@@ -50,11 +63,31 @@ impl<'a> TermionGame<'a> {
     }
 
     pub fn display(&mut self, stdout: &mut TTY) {
-        let s = self.game.to_str().replace("\n", "\n\r");
+        let s = self.game.to_str(self.kept.as_ref()).replace("\n", "\n\r");
 
         write!(stdout, "{}", termion::clear::All).expect("fail");
         write!(stdout, "{}{}", cursor::Goto(1, 1), s).expect("fail");
 
+        self.display_clocks(stdout);
+
+        if let Some(kept) = &self.kept {
+            write!(stdout, "{}kept: {}", cursor::Goto(1, 38), kept.to_str()).expect("fail");
+        }
+        if let Some(command) = &self.command {
+            write!(stdout, "{}: {}", cursor::Goto(1, 39), command).expect("fail");
+        }
+        if !self.message.is_empty() {
+            write!(
+                stdout,
+                "{}{}{}{}",
+                cursor::Goto(1, 40),
+                color::Fg(color::Red),
+                self.message,
+                RESET
+            )
+            .expect("fail");
+        }
+
         if let Some(pos) = self.pos {
             let mut x = (pos.col * 4 + 7) as u16;
             let mut y = (pos.row * 2 + 4) as u16;
@@ -78,14 +111,14 @@ impl<'a> TermionGame<'a> {
             )
             .expect("fail");
 
+            // where the move will go on the score sheet
+            let seats = self.game.seats();
+            let l = self.game.column_width();
             let s = self.game.states() - 1;
-            let mut x = 75;
-            if s % 2 == 1 {
-                x += 39;
-            }
-            let y = 4 + s / 2;
+            let x = 75 + (s % seats) * (l + 1);
+            let y = 4 + s / seats;
 
-            let m = Move::with(&self.word, pos, self.dir);
+            let m = self.game.get_board().make_move(&self.word, pos, self.dir);
             self.curr_move = Move::of(&m);
             self.valid = self.game.get_board_mut().valid_move(&m);
 
@@ -101,19 +134,21 @@ impl<'a> TermionGame<'a> {
                 write!(stdout, "{color}", color = color::Fg(color::Red)).expect("fail");
             }
 
-            write!(
-                stdout,
-                "{:<3}: {:<12}",
-                pos.to_str(self.dir),
-                self.game.get_board().format(&m, true),
-            )
-            .expect("fail");
+            let text = if l >= 38 {
+                format!("{:<3}: {:<12}", pos.to_str(self.dir), m.format(true))
+            } else {
+                // the short form of the sheet (see Game::states_str)
+                let text = format!("{} {}", pos.to_str(self.dir), m.format(true));
+                let w = l - 10;
+                format!("{:<w$}", text.chars().take(w).collect::<String>(), w = w)
+            };
+            write!(stdout, "{}", text).expect("fail");
 
             if self.valid {
                 self.game
                     .get_board_mut()
                     .score_without_sums(&mut self.curr_move);
-                let s = self.curr_move.score as u32;
+                let s = self.curr_move.score;
                 write!(
                     stdout,
                     " +{:<03}/{:<03}",
@@ -121,6 +156,9 @@ impl<'a> TermionGame<'a> {
                     self.game.get_current_player().score + s,
                 )
                 .expect("fail");
+
+                let b = self.game.get_board().breakdown(&self.curr_move);
+                write!(stdout, "{}{}", cursor::Goto(1, 39), b.to_str()).expect("fail");
             }
 
             write!(stdout, "{reset}", reset = RESET).expect("fail");
@@ -129,7 +167,7 @@ impl<'a> TermionGame<'a> {
                 write!(
                     stdout,
                     "{}{}{}{}{}",
-                    cursor::Goto((*i as u16) * 5 + 18, 36),
+                    cursor::Goto((*i as u16) * 5 + self.rack_left() + 2, 36),
                     color::Fg(color::Yellow),
                     utils::letter_with_score(&self.rack[*i], &self.game.get_board().bag),
                     RESET,
@@ -142,6 +180,31 @@ impl<'a> TermionGame<'a> {
         }
     }
 
+    // every player's time left, under the score sheet, with the player to move's in yellow
+    pub fn display_clocks(&self, stdout: &mut TTY) {
+        let clock = match self.game.clock() {
+            Some(c) => c,
+            None => return,
+        };
+
+        write!(stdout, "{}", cursor::Goto(69, 36)).expect("fail");
+        for n in 0..self.game.seats() {
+            let to_move = n == self.game.current && !self.game.finished;
+            let left = Clock::format(clock.left(n, to_move));
+            let name = &self.game.get_player(n as i32).name;
+            if to_move {
+                write!(stdout, "{}", color::Fg(color::Yellow)).expect("fail");
+            }
+            write!(stdout, "{} {:>6}{}   ", name, left, RESET).expect("fail");
+        }
+    }
+
+    fn rack_left(&self) -> u16 {
+        // column where the rack drawn by rack_to_string starts: 5 columns per tile, centered in 66
+        let width = 5 * self.game.get_board().rules.rack_size + 1;
+        ((66 - width) / 2) as u16 + 1
+    }
+
     // pub fn curr_move(&self) -> Move {
     // Move::with(&self.word, self.pos.unwrap(), self.dir)
     // }
@@ -151,7 +214,9 @@ impl<'a> TermionGame<'a> {
             return;
         } // clicked somewhere that isnt a square
         if y > 33 || x > 66 {
-            if 34 < y && y < 38 && 16 < x && x < 51 {
+            let left = self.rack_left();
+            let right = left + 5 * self.game.get_board().rules.rack_size as u16;
+            if 34 < y && y < 38 && left < x && x < right {
                 self.handle_exch(x, y);
             }
             return;
@@ -183,7 +248,7 @@ impl<'a> TermionGame<'a> {
     pub fn handle_exch(&mut self, x: u16, _y: u16) {
         self.reset(false);
 
-        let i = ((x - 16) / 5) as usize;
+        let i = ((x - self.rack_left()) / 5) as usize;
         if self.exch.contains(&i) {
             self.exch._remove_item(i);
         } else {
@@ -192,10 +257,24 @@ impl<'a> TermionGame<'a> {
     }
 
     pub fn handle_char(&mut self, c: char) {
+        if let Some(command) = &mut self.command {
+            if c == '\n' {
+                return self.handle_command();
+            }
+            command.push(c);
+            return;
+        }
+
         if c == '\n' {
             return self.handle_move();
         }
 
+        if c == ':' && self.pos.is_none() {
+            self.reset(true);
+            self.command = Some(String::new());
+            return;
+        }
+
         if let Some(pos) = self.pos {
             // must click before typing
             // todo: shift to place blank
@@ -228,26 +307,38 @@ impl<'a> TermionGame<'a> {
     pub fn handle_move(&mut self) {
         if self.valid {
             if self.exch.len() > 0 {
-                self.curr_move = Move {
-                    word: self
-                        .exch
-                        .iter()
-                        .map(|i| self.rack[*i])
-                        .sorted()
-                        .collect::<String>(),
-                    position: Position { row: 0, col: 0 },
-                    direction: Direction::Down,
-                    score: 0,
-                    evaluation: 0f32,
-                    typ: utils::Type::Exch,
-                }
+                self.curr_move = Move::exchange(&self.exch.iter().map(|i| self.rack[*i]).collect())
             }
             self.game.force_move(&self.curr_move);
             self.tick();
         }
     }
 
+    pub fn handle_command(&mut self) {
+        if let Some(command) = self.command.take() {
+            match self.game.parse_move(&command) {
+                Ok(m) => {
+                    self.message = String::new();
+                    self.game.force_move(&m);
+                    self.tick();
+                }
+                Err(e) => self.message = e,
+            }
+        }
+    }
+
+    pub fn save(&mut self, path: &str) {
+        self.message = match self.game.save(path) {
+            Ok(()) => format!("saved to {}", path),
+            Err(e) => e,
+        };
+    }
+
     pub fn handle_backspace(&mut self) {
+        if let Some(command) = &mut self.command {
+            command.pop();
+            return;
+        }
         if let Some(c) = self.word.pop() {
             self.rack.push(c);
         }
@@ -265,6 +356,9 @@ impl<'a> TermionGame<'a> {
             Event::Key(Key::Backspace) => {
                 self.handle_backspace();
             }
+            Event::Key(Key::Esc) => {
+                self.command = None;
+            }
             Event::Mouse(me) => {
                 match me {
                     // MouseEvent::Press(_, a, b) |
@@ -316,22 +410,46 @@ impl<'a> TermionGame<'a> {
         if self.game.is_over() {
             self.game.finish();
         } else {
-            if self.game.get_current_player().name == "AI" {
-                self.game.do_move(1, true); // todo: difficulty
+            if !self.game.get_current_player().is_human() {
+                self.game.do_move(true);
 
                 self.reset(true);
                 self.tick();
+            } else {
+                self.kept = self.game.infer(inference::SAMPLES);
             }
             self.reset(true);
         }
     }
 }
 
-pub fn main(p1: String, p2: String) {
-    let stdin = stdin();
+pub fn main(
+    names: Vec<String>,
+    strategies: Vec<Option<Box<dyn Strategy>>>,
+    rules: Rules,
+    load: Option<&str>,
+    save: &str,
+    time: Option<Duration>,
+    clock: Option<TimeControl>,
+) {
+    let mut g = match Game::open(load, names, strategies, rules, clock) {
+        Ok(g) => g,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    g.set_move_time(time);
+
+    // on a clock the screen is redrawn while waiting for keys, so they are polled; otherwise they are waited for
+    let timed = g.clock().is_some();
+    let mut events: Box<dyn Iterator<Item = io::Result<Event>>> = if timed {
+        Box::new(termion::async_stdin().events())
+    } else {
+        Box::new(io::stdin().events())
+    };
     let mut stdout = MouseTerminal::from(io::stdout().into_raw_mode().unwrap());
 
-    let mut g = Game::with(p1, p2);
     let mut game = TermionGame::of(&mut g);
 
     game.tick();
@@ -339,11 +457,19 @@ pub fn main(p1: String, p2: String) {
     game.display(&mut stdout);
     stdout.flush().unwrap();
 
-    for c in stdin.events() {
-        let evt = c.unwrap();
-        match evt {
-            Event::Key(Key::Ctrl('c')) => break,
-            x => game.handle(x),
+    loop {
+        match events.next() {
+            Some(Ok(Event::Key(Key::Ctrl('c')))) => break,
+            Some(Ok(Event::Key(Key::Ctrl('s')))) => game.save(save),
+            Some(Ok(x)) => game.handle(x),
+            None if !timed => break,
+            _ => {
+                // nothing typed, but the clocks run
+                thread::sleep(Duration::from_millis(50));
+                game.display_clocks(&mut stdout);
+                stdout.flush().unwrap();
+                continue;
+            }
         }
 
         game.display(&mut stdout);
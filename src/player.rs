@@ -1,107 +1,111 @@
 use crate::board::Board;
+use crate::endgame;
+use crate::strategy::{self, Budget, StaticEquity, Strategy};
 use crate::utils::ItemRemovable;
 use crate::utils::{Move, Type};
 
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Player {
     pub rack: Vec<char>,
     pub name: String,
-    pub score: u32,
+    pub score: i32,
+    #[serde(with = "strategy::by_name", default)]
+    pub strategy: Option<Box<dyn Strategy>>, // None for humans
 }
 
 impl Player {
-    pub fn gen_moves(&self, board: &mut Board, eff: bool) -> (Vec<Move>, f32) {
-        let mut gen = board.gen_all_moves(&self.rack, eff);
-        let eval_val = self.get_val(board.bag.distribution.len()); // todo implement if bag is empty, empty rack
-        gen.sort_by(Move::cmp_with(1.0, eval_val));
-        gen.dedup();
-        gen.reverse();
+    pub fn new(name: String, rack: Vec<char>, strategy: Option<Box<dyn Strategy>>) -> Player {
+        Player {
+            rack,
+            name,
+            score: 0,
+            strategy,
+        }
+    }
 
-        (gen, eval_val)
+    pub fn is_human(&self) -> bool {
+        self.strategy.is_none()
+    }
+
+    pub fn gen_moves(&self, board: &mut Board, eff: bool) -> (Vec<Move>, f32) {
+        strategy::ranked(board, &self.rack, eff)
     }
 
     /*
-    Returns: the move object, move as a human-readable string, move as a gcg string, number of moves considered
+    Chooses a move within the budget and plays it. heads_up is whether there is only one opponent,
+    which the endgame solver needs (it takes every unseen tile to be their rack).
+    Returns: the move object, move as a human-readable string, move as a gcg string
     */
     pub fn do_move(
         &mut self,
         board: &mut Board,
-        difficulty: usize,
         eff: bool,
-    ) -> (Move, String, String, usize) {
-        let moves = self.gen_moves(board, eff).0;
-        let len = moves.len();
-        let best_m = moves.iter().nth(difficulty - 1);
-
-        if let Some(m) = best_m {
-            self.score += m.score as u32;
-            match m.typ {
-                Type::Play => {
-                    let chars = board.reals(&m);
-                    let s1 = board.format(&m, true);
-                    let s2 = board.format(&m, false);
-                    board.place_move(m);
-
-                    for c in chars {
-                        if self.rack.contains(&c) {
-                            self.rack._remove_item(c);
-                        } else {
-                            self.rack._remove_item('?');
-                        }
-                    }
-
-                    self.draw_up(board);
+        budget: Budget,
+        heads_up: bool,
+    ) -> (Move, String, String) {
+        if eff {
+            board.update_cross_checks();
+        }
 
-                    return (Move::of(m), s1.clone(), s2.clone(), len);
-                }
-                Type::Exch => {
-                    let word = m.complement(&self.rack);
-                    for c in &word {
-                        self.rack._remove_item(*c);
-                        board.bag.distribution.push(*c);
+        // the solver caps itself (endgame::TIME), so untimed games hand over too
+        let solve = heads_up && board.bag.distribution.is_empty();
+        // asked to move for a human (e.g. the gui playing on), fall back to static equity
+        let m = match &mut self.strategy {
+            Some(s) if solve && s.solves_endgames() => {
+                endgame::best_move(board, &self.rack, budget)
+            }
+            Some(s) => s.choose(board, &self.rack, budget),
+            None => StaticEquity::default().choose(board, &self.rack, budget),
+        };
+
+        self.score += m.score;
+        match m.typ {
+            Type::Play => {
+                let chars = m.reals();
+                let s1 = m.format(true);
+                let s2 = m.format(false);
+                board.place_move(&m);
+
+                for c in chars {
+                    if self.rack.contains(&c) {
+                        self.rack._remove_item(c);
+                    } else {
+                        self.rack._remove_item('?');
                     }
+                }
 
-                    self.draw_up(board);
-
-                    let mut nm = Move::of(m);
-                    nm.word = word.iter().collect();
+                self.draw_up(board);
 
-                    let w = nm.word.clone();
+                (m, s1, s2)
+            }
+            Type::Exch => {
+                self.exchange(board, &m.reals());
+                let word = m.word();
 
-                    return (nm, w, String::new(), len);
-                }
+                (m, word, String::new())
             }
+            Type::Pass => (m, String::from("-"), String::from("-")),
         }
-
-        (Move::none(), String::new(), String::new(), len)
     }
 
     fn draw_up(&mut self, board: &mut Board) {
-        for c in board.bag.draw_tiles(7 - self.rack.len()) {
+        for c in board
+            .bag
+            .draw_tiles(board.rules.rack_size.saturating_sub(self.rack.len()))
+        {
             self.rack.push(c);
         }
     }
 
-    fn get_val(&self, len: usize) -> f32 {
-        /*
-        https://www.desmos.com/calculator/lkrdbcoiqt
-        Essentially, the idea is for eval to be roughly 1 the whole game,
-        but be lower as the bag decreases and exchanging/longetivity becomes impossible.
-        This method will have its primary effect to counteract blankholding.
-        */
-        let x = 1.0 - 1.0 / (4.0 * len as f32);
-        if x > 0.0 {
-            return x;
-        }
-        0.0
-    }
-
     pub fn set_rack(&mut self, rack: Vec<char>) {
         // for debugging
         self.rack = rack;
     }
 
     pub fn leave(&self, chars: Vec<char>) -> Vec<char> {
-        // pass call of board.reals(&m)
+        // pass call of m.reals()
         let mut rack = self.rack.clone();
         for c in chars {
             if rack.contains(&c) {
@@ -113,16 +117,46 @@ impl Player {
         rack
     }
 
+    /*
+    Plays a move already chosen for this player: scores it, puts it on the board
+    and draws back up (or swaps the tiles for exchanges).
+    */
+    pub fn play(&mut self, board: &mut Board, m: &Move) {
+        match m.typ {
+            Type::Play => {
+                self.remove(board, m);
+                self.score += m.score;
+                board.place_move(m);
+            }
+            Type::Exch => self.exchange(board, &m.reals()),
+            Type::Pass => {}
+        }
+    }
+
     pub fn remove(&mut self, board: &mut Board, m: &Move) {
-        self.rack = self.leave(board.reals(&m));
+        self.rack = self.leave(m.reals());
         self.draw_up(board);
     }
 
-    pub fn clone(&self) -> Player {
-        Player {
-            rack: self.rack.clone(),
-            name: self.name.clone(),
-            score: self.score,
+    pub fn exchange(&mut self, board: &mut Board, tiles: &Vec<char>) {
+        for c in tiles {
+            self.rack._remove_item(*c);
+            board.bag.distribution.push(*c);
+        }
+
+        self.draw_up(board);
+    }
+
+    pub fn has_tiles(&self, tiles: &Vec<char>) -> bool {
+        // whether the rack holds the tiles, with lowercase letters standing for blanks
+        let mut rack = self.rack.clone();
+        for c in tiles {
+            let t = if c.is_lowercase() { '?' } else { *c };
+            if !rack.contains(&t) {
+                return false;
+            }
+            rack._remove_item(t);
         }
+        true
     }
 }
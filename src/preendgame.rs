@@ -0,0 +1,211 @@
+use crate::bag::Bag;
+use crate::board::Board;
+use crate::endgame;
+use crate::player::Player;
+use crate::strategy::{ranked, Budget};
+use crate::utils::{Move, Type};
+
+use itertools::Itertools;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use std::collections::HashMap;
+use std::mem;
+use std::time::Duration;
+
+// for the whole analysis, unless given (the simulate command, the GUI's button)
+pub const TIME: Duration = Duration::from_secs(10);
+
+/*
+How a candidate fared over every way the unseen tiles could be split between the bag and the opponent.
+wins counts ties as half, spread is the average final spread.
+*/
+pub struct PreEndgameResult {
+    pub m: Move,
+    pub wins: f32,
+    pub spread: f32,
+    pub empties: bool,    // draws every tile in the bag, otherwise it fishes
+    pub scenarios: usize, // how many were tried before the budget ran out
+}
+
+impl PreEndgameResult {
+    pub fn to_str(&self) -> String {
+        format!(
+            "{:<18} {:>6.1}% {:>+8.2}  {}",
+            self.m.notation(true),
+            self.wins * 100.0,
+            self.spread,
+            if self.empties {
+                "empties bag"
+            } else {
+                "fishes"
+            }
+        )
+    }
+}
+
+/*
+Tiles in the bag, from the point of view of the player holding rack:
+everything unseen except for the opponent's full rack.
+*/
+pub fn bag_size(board: &Board, rack: &[char]) -> usize {
+    board
+        .unseen(rack)
+        .len()
+        .saturating_sub(board.rules.rack_size)
+}
+
+// (bag in draw order, opponent rack)
+type Scenario = (Vec<char>, Vec<char>);
+
+/*
+Every order the bag could be drawn in, with the opponent holding the rest of the unseen tiles.
+Returns: each scenario and how many ways it comes up.
+*/
+fn scenarios(unseen: &[char], n: usize) -> Vec<(Scenario, usize)> {
+    let mut counts = HashMap::new();
+    for order in (0..unseen.len()).permutations(n) {
+        let bag = order.iter().map(|&i| unseen[i]).collect::<Vec<char>>();
+        let mut opp = (0..unseen.len())
+            .filter(|i| !order.contains(i))
+            .map(|i| unseen[i])
+            .collect::<Vec<char>>();
+        opp.sort();
+        *counts.entry((bag, opp)).or_insert(0) += 1;
+    }
+
+    counts.into_iter().collect()
+}
+
+/*
+Plays m, then static equity for both sides until the bag is empty, then solves the endgame.
+Returns: the spread for the player of m over the rest of the game.
+*/
+fn play_out(
+    board: &mut Board,
+    m: &Move,
+    rack: &[char],
+    opp_rack: &[char],
+    depth: usize,
+    budget: Budget,
+) -> i32 {
+    let mut players = [
+        Player::new(String::new(), rack.to_vec(), None),
+        Player::new(String::new(), opp_rack.to_vec(), None),
+    ];
+    players[0].play(board, m);
+
+    let mut side = 1;
+    // a few plies at most: every play that is not a pass draws from a bag of three or fewer
+    for _ in 0..4 {
+        if board.bag.distribution.is_empty() {
+            break;
+        }
+        let moves = ranked(board, &players[side].rack, false).0;
+        let m = moves.into_iter().next().unwrap_or_else(Move::pass);
+        players[side].play(board, &m);
+        side = (side + 1) % 2;
+    }
+
+    let mut spread = players[0].score - players[1].score;
+    let racks = [players[0].rack.as_slice(), players[1].rack.as_slice()];
+    if board.went_out(&racks).is_some() {
+        // the last mover went out
+        let changes = board.settle(&racks);
+        return spread + changes[0] - changes[1];
+    }
+
+    let other = &players[(side + 1) % 2].rack;
+    let solution = endgame::solve(board, &players[side].rack, other, depth, budget);
+    if side == 0 {
+        spread += solution.value;
+    } else {
+        spread -= solution.value;
+    }
+
+    spread
+}
+
+/*
+Analyzes the best `candidates` plays by static equity when one to three tiles are left in the bag.
+Each draw is tried for every candidate, and the endgames they lead to are searched `depth` plies deep
+with an even share of what is left of the budget. Draws go in random order, and once the budget runs out
+the rest are left out, so the candidates are compared over the same sample. spread is the current spread
+for the player to move. Results are ranked by wins, then spread. The board is left as it was.
+*/
+pub fn analyze(
+    board: &mut Board,
+    rack: &[char],
+    spread: i32,
+    candidates: usize,
+    depth: usize,
+    budget: Budget,
+) -> Vec<PreEndgameResult> {
+    let unseen = board.unseen(rack);
+    let n = bag_size(board, rack);
+    if n == 0 || n > 3 {
+        return vec![];
+    }
+
+    let moves = ranked(board, rack, false)
+        .0
+        .into_iter()
+        .filter(|m| m.typ != Type::Exch)
+        .take(candidates)
+        .collect::<Vec<Move>>();
+    let mut scenarios = scenarios(&unseen, n);
+    scenarios.shuffle(&mut thread_rng());
+
+    // the bag is swapped out for ordered ones, so draws follow each scenario
+    let saved = board.save_state();
+    let real_bag = mem::replace(&mut board.bag, Bag::default());
+
+    let mut wins = vec![0.0; moves.len()];
+    let mut spreads = vec![0.0; moves.len()];
+    let mut total = 0.0;
+    let mut tried = 0;
+    let mut left = moves.len() * scenarios.len();
+    for ((bag, opp_rack), w) in scenarios.iter() {
+        if tried > 0 && budget.spent(0) {
+            break;
+        }
+        for (i, m) in moves.iter().enumerate() {
+            board.bag = Bag::with(bag);
+            let share = budget.share(1.0 / left as f32);
+            left -= 1;
+            let s = spread + play_out(board, m, rack, opp_rack, depth, share);
+            board.set_state(&saved);
+
+            if s > 0 {
+                wins[i] += *w as f32;
+            } else if s == 0 {
+                wins[i] += *w as f32 / 2.0;
+            }
+            spreads[i] += (s * *w as i32) as f32;
+        }
+        total += *w as f32;
+        tried += 1;
+    }
+
+    let mut results = Vec::new();
+    for (i, m) in moves.into_iter().enumerate() {
+        let played = m.tiles.iter().filter(|t| !t.on_board).count();
+        results.push(PreEndgameResult {
+            empties: m.typ == Type::Play && played >= n,
+            m,
+            wins: wins[i] / total,
+            spread: spreads[i] / total,
+            scenarios: tried,
+        });
+    }
+
+    board.bag = real_bag;
+    board.set_state(&saved);
+
+    results.sort_by(|a, b| {
+        b.wins
+            .partial_cmp(&a.wins)
+            .unwrap()
+            .then(b.spread.partial_cmp(&a.spread).unwrap())
+    });
+    results
+}
@@ -1,10 +1,15 @@
 use crate::game::Game;
+use crate::strategy::Handicapped;
 use base64::encode;
 
 pub fn main(turns: u32, difficulty: usize) {
     let mut game = Game::default();
+    game.set_strategies(vec![
+        Some(Box::new(Handicapped(difficulty))),
+        Some(Box::new(Handicapped(difficulty))),
+    ]);
     for _ in 0..turns {
-        game.do_move(difficulty, false);
+        game.do_move(false);
     }
 
     let p = game.get_current_player().clone();
@@ -29,7 +34,7 @@ pub fn main(turns: u32, difficulty: usize) {
             format!(
                 "{} {} {} {}\n",
                 m.position.to_str(m.direction),
-                game.get_board_mut().format(&m, true),
+                m.format(true),
                 m.score,
                 m.eval(1.0, eval_val)
             )
@@ -1,46 +1,316 @@
-use crate::board::STATE;
-use crate::game::Game;
-
-pub fn main(rack: String) {
-    let mut game = Game::default();
-
-    let mut done = false;
-    while !done {
-        println!("input ready");
-        let mut copy_state = STATE.clone();
-        let mut bag: Vec<char> = vec![];
-        for i in 0..15 {
-            let mut row = String::new();
-            std::io::stdin().read_line(&mut row).unwrap();
-            for (idx, c) in row.trim().chars().enumerate() {
-                if idx < 15 && c != '.' {
-                    let c = c.to_uppercase().nth(0).unwrap();
-                    copy_state[i][idx] = c;
-                    bag.push(c);
+use crate::board::{Board, S, STATE};
+use crate::inference::{self, Inference};
+use crate::player::Player;
+use crate::preendgame;
+use crate::strategy::{leave_weight, ranked, Budget, Strategy};
+use crate::utils::Move;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use std::time::Duration;
+
+/*
+Monte Carlo simulation of candidate moves.
+
+Each iteration draws an opponent rack from the unseen tiles (weighted by what they
+probably kept, when that is known), then for every candidate
+plays it and continues for a few plies with both sides playing static equity.
+A candidate is valued by the spread over those plies plus the value of the leave
+after our last move. All candidates see the same draws in an iteration, so the
+comparison between them is less noisy than the values themselves.
+*/
+pub struct SimResult {
+    pub m: Move,
+    pub equity: f32, // mean of spread + leave
+    pub spread: f32,
+    pub leave: f32,
+    pub stdev: f32,
+    pub iterations: usize,
+}
+
+impl SimResult {
+    // half the width of the 95% confidence interval of the equity
+    pub fn margin(&self) -> f32 {
+        1.96 * self.stdev / (self.iterations as f32).sqrt()
+    }
+
+    pub fn to_str(&self) -> String {
+        format!(
+            "{:<18} {:>+8.2} ±{:<6.2} {:>+8.2} {:>7.2}",
+            self.m.notation(true),
+            self.equity,
+            self.margin(),
+            self.spread,
+            self.leave
+        )
+    }
+}
+
+/*
+Returns: (spread, leave value) of one playout of m, with the opponent holding opp_rack.
+The board's bag must already hold what the opponent could draw.
+*/
+fn play_out(
+    board: &mut Board,
+    m: &Move,
+    rack: &[char],
+    opp_rack: &[char],
+    plies: usize,
+) -> (f32, f32) {
+    let mut players = [
+        Player::new(String::new(), rack.to_vec(), None),
+        Player::new(String::new(), opp_rack.to_vec(), None),
+    ];
+
+    let mut leave = m.evaluation * leave_weight(board.bag.distribution.len());
+    players[0].play(board, m);
+
+    for ply in 1..plies {
+        if players[(ply + 1) % 2].rack.is_empty() {
+            break; // the last mover went out
+        }
+
+        // plain static equity, even with the bag empty: solving endgames here would be far too slow
+        let (moves, weight) = ranked(board, &players[ply % 2].rack, false);
+        let m = moves.into_iter().next().unwrap_or_else(Move::pass);
+        players[ply % 2].play(board, &m);
+        if ply % 2 == 0 {
+            leave = m.evaluation * weight;
+        }
+    }
+
+    let mut spread = players[0].score as f32 - players[1].score as f32;
+    let racks = [players[0].rack.as_slice(), players[1].rack.as_slice()];
+    if board.went_out(&racks).is_some() {
+        let changes = board.settle(&racks);
+        spread += (changes[0] - changes[1]) as f32;
+        leave = 0.0;
+    }
+
+    (spread, leave)
+}
+
+/*
+Simulates the best `candidates` moves by static equity, `iterations` times each, `plies` deep
+(counting the candidate itself). Opponent racks are drawn from inference if given, else uniformly.
+Stops early when the budget runs out (a node is one playout), dropping the iteration it is on
+so every candidate has seen the same draws.
+Returns the results ranked by equity.
+*/
+pub fn simulate(
+    board: &mut Board,
+    rack: &[char],
+    candidates: usize,
+    plies: usize,
+    iterations: usize,
+    inference: Option<&Inference>,
+    budget: Budget,
+) -> Vec<SimResult> {
+    let moves = ranked(board, rack, false)
+        .0
+        .into_iter()
+        .take(candidates)
+        .collect::<Vec<Move>>();
+    let unseen = board.unseen(rack);
+    let saved = board.save_state();
+
+    // (spread, leave) of each playout, per candidate
+    let mut outcomes = vec![Vec::new(); moves.len()];
+    'iterations: for i in 0..iterations {
+        let n = board.rules.rack_size.min(unseen.len());
+        let (opp_rack, pool) = match inference {
+            Some(inf) => inf.sample(&unseen, n),
+            None => {
+                let mut pool = unseen.clone();
+                pool.shuffle(&mut thread_rng());
+                let opp_rack = pool.split_off(pool.len() - n);
+                (opp_rack, pool)
+            }
+        };
+
+        for (j, m) in moves.iter().enumerate() {
+            if budget.spent(i * moves.len() + j) {
+                for outcome in outcomes.iter_mut() {
+                    outcome.truncate(i);
                 }
+                break 'iterations;
             }
+            board.bag.distribution = pool.clone();
+            outcomes[j].push(play_out(board, m, rack, &opp_rack, plies));
+            board.set_state(&saved);
         }
-        game.set_board(copy_state);
-        println!("{}", game.get_board());
-        done = true;
     }
 
-    game.get_player_mut(0).set_rack(rack.chars().collect());
+    let mut results = moves
+        .into_iter()
+        .zip(outcomes)
+        .map(|(m, outcome)| {
+            let n = outcome.len().max(1) as f32;
+            let values = outcome.iter().map(|(s, l)| s + l).collect::<Vec<f32>>();
+            let equity = values.iter().sum::<f32>() / n;
+            let var = values.iter().map(|v| (v - equity).powi(2)).sum::<f32>() / (n - 1.0).max(1.0);
 
-    let (m1, sm1, _, _nmoves1) = game.do_move(1, false);
+            SimResult {
+                m,
+                equity,
+                spread: outcome.iter().map(|(s, _)| s).sum::<f32>() / n,
+                leave: outcome.iter().map(|(_, l)| l).sum::<f32>() / n,
+                stdev: var.sqrt(),
+                iterations: outcome.len(),
+            }
+        })
+        .collect::<Vec<SimResult>>();
 
-    println!(
-        "{}",
-        format!(
-            "{:<7}/{:<3}: {:<12} +{:<03}/{:<03}",
+    results.sort_by(|a, b| b.equity.partial_cmp(&a.equity).unwrap());
+    results
+}
+
+/*
+Picks the move with the best simulated equity.
+*/
+#[derive(Clone)]
+pub struct Simulation {
+    pub candidates: usize,
+    pub plies: usize,
+    pub iterations: usize,
+    pub inference: Option<Inference>, // from the opponent's last move
+    pub observed: Option<(S, Move, Vec<char>)>, // that move, until it is our turn to infer from it
+}
+
+impl Simulation {
+    pub fn default() -> Simulation {
+        Simulation {
+            candidates: 5,
+            plies: 2,
+            iterations: 20,
+            inference: None,
+            observed: None,
+        }
+    }
+}
+
+impl Strategy for Simulation {
+    fn name(&self) -> String {
+        format!("sim:{}", self.iterations)
+    }
+
+    fn solves_endgames(&self) -> bool {
+        true
+    }
+
+    fn choose(&mut self, board: &mut Board, rack: &[char], budget: Budget) -> Move {
+        // inferring on our own time, not the opponent's, with a quarter of it
+        if let Some((before, m, pool)) = self.observed.take() {
+            let now = board.save_state();
+            board.set_state(&before);
+            self.inference =
+                inference::infer(board, &m, &pool, inference::SAMPLES, budget.share(0.25));
+            board.set_state(&now);
+        }
+
+        let results = simulate(
+            board,
             rack,
-            m1.position.to_str(m1.direction),
-            sm1,
-            m1.score,
-            game.get_player(0).score,
-        )
+            self.candidates,
+            self.plies,
+            self.iterations,
+            self.inference.as_ref(),
+            budget,
+        );
+        match results.into_iter().next() {
+            Some(r) => r.m,
+            None => Move::pass(),
+        }
+    }
+
+    fn infers(&self) -> bool {
+        true
+    }
+
+    fn observe(&mut self, before: &mut Board, m: &Move, pool: &[char]) {
+        self.observed = Some((before.save_state(), Move::of(m), pool.to_vec()));
+    }
+
+    fn box_clone(&self) -> Box<dyn Strategy> {
+        Box::new(self.clone())
+    }
+}
+
+/*
+Reads a board from stdin, one row per line ('.' for empty squares, short rows are padded).
+*/
+pub fn read_board() -> Board {
+    let mut board = Board::default();
+
+    println!("input ready");
+    let mut state = STATE;
+    for row in state.iter_mut() {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).unwrap();
+        for (idx, c) in line.trim().chars().take(15).enumerate() {
+            if c != '.' {
+                row[idx] = c.to_uppercase().next().unwrap();
+            }
+        }
+    }
+    board.set_board(state);
+
+    board
+}
+
+/*
+Reads a board (see read_board) and prints the simulated ranking of the moves for the rack.
+With one to three tiles left in the bag, every draw is tried instead (see preendgame::analyze),
+using spread (the current score difference) to count wins, and depth and time (seconds, in all)
+for the endgames.
+*/
+pub fn main(
+    rack: String,
+    candidates: usize,
+    plies: usize,
+    iterations: usize,
+    spread: i32,
+    depth: usize,
+    time: u64,
+) {
+    let mut board = read_board();
+    let rack = rack.to_uppercase().chars().collect::<Vec<char>>();
+    // the opponent's rack is somewhere in here too
+    board.bag.distribution = board.unseen(&rack);
+    println!("{}", board);
+
+    let n = preendgame::bag_size(&board, &rack);
+    if n > 0 && n <= 3 {
+        println!("{} in the bag, trying every draw", n);
+        println!("{:<4}{:<18} {:>7} {:>8}", "", "move", "wins", "spread");
+        let budget = Budget::time(Duration::from_secs(time));
+        for (i, r) in preendgame::analyze(&mut board, &rack, spread, candidates, depth, budget)
+            .iter()
+            .enumerate()
+        {
+            println!("{:<4}{}", format!("{}.", i + 1), r.to_str());
+        }
+        return;
+    }
+
+    println!(
+        "{:<4}{:<18} {:>8} {:<7} {:>8} {:>7}",
+        "", "move", "equity", " 95%", "spread", "leave"
     );
-    println!("{}", game.get_board());
+    for (i, r) in simulate(
+        &mut board,
+        &rack,
+        candidates,
+        plies,
+        iterations,
+        None,
+        Budget::unlimited(),
+    )
+    .iter()
+    .enumerate()
+    {
+        println!("{:<4}{}", format!("{}.", i + 1), r.to_str());
+    }
 }
 
 /*
@@ -0,0 +1,343 @@
+use crate::board::Board;
+use crate::difficulty::{self, Level};
+use crate::equity::Equity;
+use crate::simulate::Simulation;
+use crate::utils::{Move, Type};
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::time::{Duration, Instant};
+
+/*
+How an AI player picks its move, given the board (which knows the bag and rules) and its rack.
+Human players have no strategy.
+*/
+pub trait Strategy: Send {
+    // the name it is chosen by on the command line (see from_name)
+    fn name(&self) -> String;
+
+    // should return its best move so far once the budget runs out
+    fn choose(&mut self, board: &mut Board, rack: &[char], budget: Budget) -> Move;
+
+    // called with each opponent move m, on the board as it was before it; pool is every tile unseen by this player
+    fn observe(&mut self, _before: &mut Board, _m: &Move, _pool: &[char]) {}
+
+    // whether observe is used (to infer opponent racks); if not, the game skips setting it up
+    fn infers(&self) -> bool {
+        false
+    }
+
+    // whether to hand over to the endgame solver once the bag is empty (see Player::do_move)
+    fn solves_endgames(&self) -> bool {
+        false
+    }
+
+    fn box_clone(&self) -> Box<dyn Strategy>;
+}
+
+/*
+How long a strategy may think about a move: until a deadline, and/or for a number of nodes
+(positions searched in an endgame, playouts in a simulation). Strategies that only rank
+one generation of moves (static equity and the like) finish in one step either way.
+*/
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Budget {
+    pub deadline: Option<Instant>,
+    pub nodes: Option<usize>,
+}
+
+impl Budget {
+    pub fn unlimited() -> Budget {
+        Budget::default()
+    }
+
+    pub fn time(time: Duration) -> Budget {
+        Budget {
+            deadline: Some(Instant::now() + time),
+            nodes: None,
+        }
+    }
+
+    // no later than `time` from now, and no later than it already was
+    pub fn at_most(self, time: Duration) -> Budget {
+        let limit = Instant::now() + time;
+        Budget {
+            deadline: Some(self.deadline.map_or(limit, |d| d.min(limit))),
+            ..self
+        }
+    }
+
+    // the first `fraction` of what is left, for a step that must leave time for the rest
+    pub fn share(self, fraction: f32) -> Budget {
+        let now = Instant::now();
+        Budget {
+            deadline: self
+                .deadline
+                .map(|d| now + d.saturating_duration_since(now).mul_f32(fraction)),
+            nodes: self.nodes.map(|n| (n as f32 * fraction) as usize),
+        }
+    }
+
+    pub fn spent(&self, nodes: usize) -> bool {
+        matches!(self.deadline, Some(d) if Instant::now() >= d)
+            || matches!(self.nodes, Some(n) if nodes >= n)
+    }
+}
+
+impl Clone for Box<dyn Strategy> {
+    fn clone(&self) -> Box<dyn Strategy> {
+        self.box_clone()
+    }
+}
+
+/*
+Names:
+    human            no strategy, moves are entered by hand
+    equity (or ai)   best static equity, score plus leave
+    equity:CONFIG    the same, weighted by a config file or inline weights (see equity::Equity)
+    score            highest scoring move
+    random           any legal move
+    handicapped:N    the Nth best move by static equity
+    sim or sim:N     best of a Monte Carlo simulation, N iterations
+    lookahead[:N]    best of the top N (default 10) by static equity, less the opponent's best reply
+    level:NAME       plays like a person: beginner, junior, club or expert (see difficulty::PROFILES)
+*/
+pub fn from_name(name: &str) -> Result<Option<Box<dyn Strategy>>, String> {
+    let lower = name.to_lowercase();
+    let s: Box<dyn Strategy> = match lower.as_str() {
+        "human" => return Ok(None),
+        "equity" | "ai" => Box::new(StaticEquity::default()),
+        "score" => Box::new(HighestScore),
+        "random" => Box::new(RandomMove),
+        "sim" => Box::new(Simulation::default()),
+        _ => match lower
+            .strip_prefix("handicapped:")
+            .map(|n| n.parse::<usize>())
+        {
+            Some(Ok(n)) if n > 0 => Box::new(Handicapped(n)),
+            _ => match lower.strip_prefix("sim:").map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) if n > 0 => Box::new(Simulation {
+                    iterations: n,
+                    ..Simulation::default()
+                }),
+                _ if lower == "lookahead" => Box::new(Lookahead(10)),
+                _ if lower.starts_with("lookahead:") => {
+                    match lower["lookahead:".len()..].parse::<usize>() {
+                        Ok(n) if n > 0 => Box::new(Lookahead(n)),
+                        _ => return Err(format!("bad candidate count in {}", name)),
+                    }
+                }
+                _ if lower.starts_with("level:") => {
+                    match difficulty::profile(&lower["level:".len()..]) {
+                        Some(p) => Box::new(Level::new(p)),
+                        None => return Err(format!("unknown level {}", name)),
+                    }
+                }
+                // config files keep the case they were given in
+                _ if lower.starts_with("equity:") => {
+                    Box::new(StaticEquity(Equity::parse(&name["equity:".len()..])?))
+                }
+                _ => return Err(format!("unknown strategy {}", name)),
+            },
+        },
+    };
+
+    Ok(Some(s))
+}
+
+// for saving players: strategies are written by name
+pub mod by_name {
+    use super::*;
+
+    pub fn serialize<Ser: Serializer>(
+        s: &Option<Box<dyn Strategy>>,
+        serializer: Ser,
+    ) -> Result<Ser::Ok, Ser::Error> {
+        s.as_ref().map(|s| s.name()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Box<dyn Strategy>>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(name) => from_name(&name).map_err(D::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+// leave weight of the default equity config
+pub fn leave_weight(len: usize) -> f32 {
+    Equity::default().leave_weight(len)
+}
+
+/*
+Returns: every move for the rack, best static equity (default config) first, and the weight given to leaves.
+Simulation, lookahead, the levels, pre-endgames, inference and leave training all rank this way,
+whatever config the player has; only StaticEquity uses its own.
+*/
+pub fn ranked(board: &mut Board, rack: &[char], eff: bool) -> (Vec<Move>, f32) {
+    Equity::default().ranked(board, rack, eff)
+}
+
+/*
+Plays the best move by static equity, valued by its config (see equity::Equity).
+*/
+#[derive(Clone, Default)]
+pub struct StaticEquity(pub Equity);
+
+impl Strategy for StaticEquity {
+    fn name(&self) -> String {
+        if self.0 == Equity::default() {
+            String::from("equity")
+        } else {
+            format!("equity:{}", self.0.to_spec())
+        }
+    }
+
+    fn solves_endgames(&self) -> bool {
+        true
+    }
+
+    fn choose(&mut self, board: &mut Board, rack: &[char], _budget: Budget) -> Move {
+        let moves = self.0.ranked(board, rack, false).0;
+        moves.into_iter().next().unwrap_or_else(Move::pass)
+    }
+
+    fn box_clone(&self) -> Box<dyn Strategy> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone)]
+pub struct HighestScore;
+
+impl Strategy for HighestScore {
+    fn name(&self) -> String {
+        String::from("score")
+    }
+
+    fn choose(&mut self, board: &mut Board, rack: &[char], _budget: Budget) -> Move {
+        let (moves, eval_val) = ranked(board, rack, false);
+        // equity only breaks ties
+        let best = moves.iter().max_by(|x, y| {
+            x.score
+                .cmp(&y.score)
+                .then(Move::cmp_with(1.0, eval_val)(x, y))
+        });
+
+        match best {
+            Some(m) => Move::of(m),
+            None => Move::pass(),
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn Strategy> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone)]
+pub struct RandomMove;
+
+impl Strategy for RandomMove {
+    fn name(&self) -> String {
+        String::from("random")
+    }
+
+    fn choose(&mut self, board: &mut Board, rack: &[char], _budget: Budget) -> Move {
+        let moves = board.gen_all_moves(&rack.to_vec(), false);
+        match moves.choose(&mut thread_rng()) {
+            Some(m) => Move::of(m),
+            None => Move::pass(),
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn Strategy> {
+        Box::new(self.clone())
+    }
+}
+
+/*
+Plays the nth best move by static equity (1 is the best), or the worst if there are fewer.
+*/
+#[derive(Clone)]
+pub struct Handicapped(pub usize);
+
+impl Strategy for Handicapped {
+    fn name(&self) -> String {
+        format!("handicapped:{}", self.0)
+    }
+
+    fn choose(&mut self, board: &mut Board, rack: &[char], _budget: Budget) -> Move {
+        let moves = ranked(board, rack, false).0;
+        match moves.get(self.0 - 1).or_else(|| moves.last()) {
+            Some(m) => Move::of(m),
+            None => Move::pass(),
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn Strategy> {
+        Box::new(self.clone())
+    }
+}
+
+/*
+Looks one reply ahead: each of the best n moves by static equity is played on the board, and the
+opponent's best static reply is found for a rack drawn from the unseen tiles. The move's value is its
+own equity less the reply's. All candidates face the same rack, so one draw is enough to compare them,
+though not to value them; for that there is sim.
+*/
+#[derive(Clone)]
+pub struct Lookahead(pub usize);
+
+impl Strategy for Lookahead {
+    fn name(&self) -> String {
+        format!("lookahead:{}", self.0)
+    }
+
+    fn solves_endgames(&self) -> bool {
+        true
+    }
+
+    fn choose(&mut self, board: &mut Board, rack: &[char], budget: Budget) -> Move {
+        let (moves, eval_val) = ranked(board, rack, false);
+
+        let mut pool = board.unseen(rack);
+        pool.shuffle(&mut thread_rng());
+        let opp_rack = pool.split_off(pool.len() - board.rules.rack_size.min(pool.len()));
+
+        let saved = board.save_state();
+        let mut best: Option<(f32, &Move)> = None;
+        for (i, m) in moves.iter().take(self.0).enumerate() {
+            // the first candidate is always looked at, so there is a move to return
+            if i > 0 && budget.spent(i) {
+                break;
+            }
+
+            if m.typ == Type::Play {
+                board.place_move(m);
+            }
+            board.bag.distribution = pool.clone();
+            let (replies, weight) = ranked(board, &opp_rack, false);
+            let reply = replies.first().map_or(0.0, |r| r.eval(1.0, weight));
+            board.set_state(&saved);
+
+            let value = m.eval(1.0, eval_val) - reply;
+            if !matches!(best, Some((v, _)) if v >= value) {
+                best = Some((value, m));
+            }
+        }
+
+        match best {
+            Some((_, m)) => Move::of(m),
+            None => Move::pass(),
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn Strategy> {
+        Box::new(self.clone())
+    }
+}
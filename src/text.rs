@@ -1,160 +1,132 @@
+use crate::board::Rules;
+use crate::clock::TimeControl;
 use crate::game::Game;
-use crate::utils::Type;
-use std::time::SystemTime;
+use crate::strategy::Strategy;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{Duration, SystemTime};
 
-fn two_player_game(g: &mut Game, gcg: bool) {
+fn play_game(g: &mut Game, gcg: bool) {
     // b.bag = bag::Bag::with(&vec!['S', 'D', 'L', 'A', 'N', '?', 'A', 'U', 'E', 'M', 'S', 'R', 'A', 'C', 'Z', 'E', 'P', 'F', 'T', 'I', 'R', 'O', 'E', 'N', 'F', 'O', 'O', 'Y', 'A', 'N', 'I', 'U', 'L', 'M', 'R', 'E', 'B', 'E', 'A', 'U', 'B', 'A', 'T', 'I', 'L', 'W', 'V', 'N', 'E', 'A', 'G', 'T', 'O', 'O', 'E', 'H', 'A', 'K', 'U', 'R', 'D', 'I', 'I', '?', 'D', 'T', 'V', 'Y', 'N', 'I', 'E', 'Q', 'J', 'S', 'D', 'L', 'E', 'R', 'O', 'E', 'X', 'A', 'I', 'H', 'W', 'O', 'I', 'C', 'P', 'T', 'S', 'R', 'N', 'E', 'T', 'O', 'G', 'G', 'I', 'E']);
     let mut out = String::new();
 
-    if gcg {
-        let p1 = g.get_player(0).name.clone();
-        let p2 = g.get_player(1).name.clone();
-        out = format!(
-            "#character-encoding UTF-8\n#player1 {n1} {n1}\n#player2 {n2} {n2}",
-            n1 = p1,
-            n2 = p2
-        );
-    }
-
-    let mut turn = 1;
-
     while !g.is_over() {
-        let rack_1: String = g.get_player(0).rack.clone().iter().collect();
-        let start1 = SystemTime::now();
-        let (m1, _, sm1, _nmoves1) = g.do_move(1, false);
-        let time1 = start1.elapsed().expect("Time went backwards").as_millis();
-
-        if sm1 == String::new() && m1.typ == Type::Play {
-            break;
-        }
-
-        if gcg {
-            let p = g.get_player(0);
-            out = match m1.typ {
-                Type::Play => format!(
-                    "{}\n>{}: {} {} {} +{} {}",
-                    out,
-                    p.name,
-                    rack_1,
-                    m1.position.to_str(m1.direction),
-                    sm1,
-                    m1.score,
-                    p.score
-                ),
-                Type::Exch => format!(
-                    "{}\n>{}: {} -{} +0 {}",
-                    out, p.name, rack_1, m1.word, p.score
-                ),
-            }
-        } else {
-            out = format!(
-                "{}\n{:<02}. {:<7}/{:<3}: {:<12} +{:<03}/{:<03} ({:<04})",
-                out,
-                turn,
-                rack_1,
-                m1.position.to_str(m1.direction),
-                sm1,
-                m1.score,
-                g.get_player(0).score,
-                time1
-            );
-        }
-
-        if gcg {
-            out = format!("{}\n#note Time: {}", out, time1);
-        }
-
-        if g.get_player(0).rack.len() == 0 {
-            break;
-        }
-
-        let rack_2: String = g.get_player(1).rack.clone().iter().collect();
-        let start2 = SystemTime::now();
-        let (m2, _, sm2, _nmoves2) = g.do_move(1, false);
-        let time2 = start2.elapsed().expect("Time went backwards").as_millis();
-
-        if sm2 == String::new() && m2.typ == Type::Play {
-            break;
-        }
-
-        if gcg {
-            let p = g.get_player(1);
-            out = match m2.typ {
-                Type::Play => format!(
-                    "{}\n>{}: {} {} {} +{} {}",
-                    out,
-                    p.name,
-                    rack_2,
-                    m2.position.to_str(m2.direction),
-                    sm2,
-                    m2.score,
-                    p.score
-                ),
-                Type::Exch => format!(
-                    "{}\n>{}: {} -{} +0 {}",
-                    out, p.name, rack_2, m2.word, p.score
-                ),
-            }
-        } else {
+        let n = g.current;
+        let turn = g.get_turn();
+        let rack: String = g.get_current_player().rack.iter().collect();
+        let start = SystemTime::now();
+        let (m, _, sm) = g.do_move(false);
+        let time = start.elapsed().expect("Time went backwards").as_millis();
+
+        let p = g.get_player(n as i32);
+        if !gcg {
+            // a line per round, a column per player
+            let prefix = if n == 0 {
+                format!("\n{:<02}.", turn)
+            } else {
+                String::from(" |")
+            };
             out = format!(
-                "{} | {:<7}/{:<3}: {:<12} +{:<03}/{:<03} ({:<04})",
+                "{}{} {:<7}/{:<3}: {:<12} +{:<03}/{:<03} ({:<04})",
                 out,
-                rack_2,
-                m2.position.to_str(m2.direction),
-                sm2,
-                m2.score,
-                g.get_player(1).score,
-                time2
+                prefix,
+                rack,
+                m.position.to_str(m.direction),
+                sm,
+                m.score,
+                p.score,
+                time
             );
         }
 
-        if gcg {
-            out = format!("{}\n#note Time: {}", out, time2);
-        }
-
-        turn += 1;
-
         //         println!("{}", out);
         //         println!("{}", g.get_board());
 
-        println!("{}", g.to_str());
+        if g.current == 0 {
+            println!("{}", g.to_str(None));
+        }
     }
 
-    let (end_s, end, n) = g.finish();
-    if n == 0 {
-        if gcg {
-            let p = g.get_player(0);
-            out = format!("{}\n>{}:  ({}) +{} {}", out, p.name, end_s, end, p.score);
-        } else {
-            out = format!("{}\n 2*({}) +{}/{}", out, end_s, end, g.get_player(0).score);
-        }
+    let ending = g.finish();
+    if gcg {
+        out = format!("{}#note {}", g.to_gcg(), g.result());
     } else {
-        if gcg {
-            let p = g.get_player(1);
-            out = format!("{}\n>{}:  ({}) +{} {}", out, p.name, end_s, end, p.score);
-        } else {
-            out = format!(
-                "{}\n {} 2*({}) +{}/{}",
-                out,
-                " ".repeat(45),
-                end_s,
-                end,
-                g.get_player(1).score
-            );
+        for n in 0..g.seats() {
+            if let Some(text) = ending.notation(n) {
+                let p = g.get_player(n as i32);
+                let indent = 1 + 46 * n;
+                out = format!("{}\n{}{}/{}", out, " ".repeat(indent), text, p.score);
+            }
         }
-    }
-    if !gcg {
-        out = format!("{}\n{}", out, g.get_board());
+        out = format!("{}\n{}\n{}", out, g.result(), g.get_board());
     }
     println!("{}", out);
 }
 
-pub fn main(n: u32) {
-    let mut game = Game::default();
+pub fn main(
+    n: u32,
+    rules: Rules,
+    json: Option<&str>,
+    strategies: Vec<Option<Box<dyn Strategy>>>,
+    time: Option<Duration>,
+    clock: Option<TimeControl>,
+) {
+    let seats = strategies.len();
+    let names = (1..=seats).map(|i| format!("p{}", i)).collect();
+    let mut game = Game::with_players(names, rules);
+    game.set_strategies(strategies);
+    game.set_move_time(time);
+    game.set_clock(clock);
+    // wins for each player, then ties, and total scores
+    let mut results = vec![0; seats + 1];
+    let mut scores = vec![0; seats];
     for _ in 0..n {
-        two_player_game(&mut game, true);
+        play_game(&mut game, true);
+        results[game.winner().unwrap_or(seats)] += 1;
+        for (i, total) in scores.iter_mut().enumerate() {
+            *total += game.get_player(i as i32).score;
+        }
+        if let Some(path) = json {
+            // one game per line, for scripts
+            let mut f = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .expect("could not open json file");
+            writeln!(f, "{}", game.to_json()).expect("could not write json file");
+        }
         game.reset();
     }
+
+    if n > 1 {
+        // to compare strategies (e.g. two equity configs) over many games
+        let p = |i: usize| {
+            let name = match &game.get_player(i as i32).strategy {
+                Some(s) => s.name(),
+                None => String::from("human"),
+            };
+            format!("{} ({})", game.get_player(i as i32).name, name)
+        };
+        let average = |i: usize| scores[i] as f32 / n as f32;
+        if seats == 2 {
+            println!(
+                "{} {} - {} {}, {} tied, average score {:.1} - {:.1}",
+                p(0),
+                results[0],
+                results[1],
+                p(1),
+                results[2],
+                average(0),
+                average(1)
+            );
+        } else {
+            // one line a seat, since a row of scores is hard to match up with three or four players
+            for (i, wins) in results.iter().enumerate().take(seats) {
+                println!("{} wins {}, average score {:.1}", p(i), wins, average(i));
+            }
+            println!("{} tied", results[seats]);
+        }
+    }
     // let mut b = bag::Bag::default();
     // println!("Score for z is: {}", bag.score('z'));
 
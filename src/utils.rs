@@ -2,10 +2,13 @@ use itertools::{EitherOrBoth::*, Itertools};
 use std::char::from_u32;
 use std::cmp::Ordering;
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io::Write;
 use std::ops::Range;
 use std::slice::Iter;
 
+use serde::{Deserialize, Serialize};
+
 use crate::bag::Bag;
 
 pub trait ItemRemovable<T> {
@@ -31,13 +34,13 @@ impl<T: PartialEq> ItemCountable<T> for Vec<T> {
 
 pub static ALPH: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ?";
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Position {
     pub row: usize,
     pub col: usize,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Direction {
     Across,
     Down,
@@ -175,6 +178,34 @@ impl Position {
         }
     }
 
+    /*
+    Inverse of to_str: "8H" is across (row first), "H8" is down (column first).
+    */
+    pub fn parse(s: &str) -> Option<(Position, Direction)> {
+        let s = s.trim().to_uppercase();
+        let first = s.chars().next()?;
+
+        let (dir, letter, number) = if first.is_ascii_digit() {
+            let i = s.find(|c: char| !c.is_ascii_digit())?;
+            (Direction::Across, &s[i..], &s[..i])
+        } else if first.is_ascii_alphabetic() {
+            (Direction::Down, &s[..1], &s[1..])
+        } else {
+            return None;
+        };
+
+        if letter.len() != 1 {
+            return None;
+        }
+        let col = ALPH.find(letter)?;
+        let row = number.parse::<usize>().ok()?;
+        if col >= 15 || !(1..=15).contains(&row) {
+            return None;
+        }
+
+        Some((Position { row: row - 1, col }, dir))
+    }
+
     pub fn tick_n(&self, d: Direction, n: u32) -> Option<Position> {
         let mut p = self.clone();
         for _ in 0..n {
@@ -208,15 +239,39 @@ pub fn positions() -> Vec<Position> {
         .collect::<Vec<Position>>()
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Type {
     Play,
     Exch,
+    Pass,
+}
+
+/*
+One tile of a move: where it goes, which letter it is, whether it is a blank
+standing for that letter, and whether it was already on the board before the move.
+For exchanges, the tiles are the ones put back in the bag.
+*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Tile {
+    pub position: Position,
+    pub letter: char,
+    pub blank: bool,
+    pub on_board: bool,
 }
 
-#[derive(Debug)]
+impl Tile {
+    pub fn shown(&self) -> char {
+        // the letter as written in notation: lowercase for blanks
+        if self.blank {
+            return self.letter.to_ascii_lowercase();
+        }
+        self.letter
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Move {
-    pub word: String,
+    pub tiles: Vec<Tile>,
     pub position: Position,
     pub direction: Direction,
     pub score: i32,
@@ -253,19 +308,12 @@ impl Move {
 
 impl Move {
     pub fn of(m: &Move) -> Move {
-        Move {
-            word: m.word.clone(),
-            position: m.position.clone(),
-            direction: m.direction.clone(),
-            score: m.score.clone(),
-            evaluation: m.evaluation.clone(),
-            typ: m.typ.clone(),
-        }
+        m.clone()
     }
 
     pub fn none() -> Move {
         Move {
-            word: String::new(),
+            tiles: Vec::new(),
             position: Position { row: 0, col: 0 },
             direction: Direction::Down,
             score: 0,
@@ -274,68 +322,199 @@ impl Move {
         }
     }
 
-    pub fn with(word: &String, pos: Position, dir: Direction) -> Move {
+    pub fn pass() -> Move {
         let mut m = Move::none();
-        m.word = word.clone();
-        m.position = pos;
-        m.direction = dir;
+        m.typ = Type::Pass;
 
         m
     }
 
-    pub fn complement(&self, rack: &Vec<char>) -> Vec<char> {
-        let mut nr = rack.clone();
+    pub fn exchange(tiles: &Vec<char>) -> Move {
+        // sorted, so the same exchange always compares equal
+        let mut m = Move::none();
+        m.typ = Type::Exch;
+        for c in tiles.iter().sorted() {
+            m.tiles.push(Tile {
+                position: m.position,
+                letter: *c,
+                blank: false,
+                on_board: false,
+            });
+        }
+
+        m
+    }
+
+    pub fn word(&self) -> String {
+        self.tiles.iter().map(|t| t.shown()).collect()
+    }
 
-        for c in self.word.chars() {
-            nr._remove_item(c);
+    pub fn reals(&self) -> Vec<char> {
+        // tiles that come from the rack, with blanks as lowercase letters
+        self.tiles
+            .iter()
+            .filter(|t| !t.on_board)
+            .map(|t| t.shown())
+            .collect()
+    }
+
+    pub fn format(&self, human: bool) -> String {
+        let mut res = String::new();
+        for t in self.tiles.iter() {
+            if !t.on_board {
+                res.push(t.shown());
+            } else if human {
+                res.push('(');
+                res.push(t.shown());
+                res.push(')');
+            } else {
+                res.push('.');
+            }
+        }
+
+        res.replace(")(", "")
+    }
+
+    pub fn notation(&self, human: bool) -> String {
+        // "8H WO(R)D" as typed into parse_move if human, else "8H WO.D" as in gcg; "-ABC" or "-" either way
+        match self.typ {
+            Type::Play => format!(
+                "{} {}",
+                self.position.to_str(self.direction),
+                self.format(human)
+            ),
+            Type::Exch => format!("-{}", self.word()),
+            Type::Pass => String::from("-"),
         }
+    }
 
-        nr
+    pub fn iter(&self) -> impl Iterator<Item = (Position, char)> + '_ {
+        self.tiles.iter().map(|t| (t.position, t.shown()))
     }
 
     pub fn exch(&self) -> bool {
         self.typ == Type::Exch
     }
+
+    pub fn is_pass(&self) -> bool {
+        self.typ == Type::Pass
+    }
 }
 
-pub struct IterMove {
-    _m: Move,
-    _curr: u32,
+/*
+One scored word of a move: every letter with its face value and letter multiplier,
+and the word multiplier applied to their sum.
+*/
+#[derive(Debug, Clone)]
+pub struct WordScore {
+    pub word: String,
+    pub letters: Vec<(char, i32, i32)>,
+    pub word_mult: i32,
+    pub score: i32,
 }
 
-impl Move {
-    pub fn iter(&self) -> IterMove {
-        IterMove {
-            _m: Move::of(self),
-            _curr: 0,
+impl WordScore {
+    pub fn new() -> WordScore {
+        WordScore {
+            word: String::new(),
+            letters: Vec::new(),
+            word_mult: 1,
+            score: 0,
+        }
+    }
+
+    pub fn push(&mut self, c: char, value: i32, letter_mult: i32) {
+        self.word.push(c);
+        self.letters.push((c, value, letter_mult));
+    }
+
+    pub fn tally(&mut self) -> i32 {
+        self.score = self.word_mult * self.letters.iter().map(|(_, v, m)| v * m).sum::<i32>();
+        self.score
+    }
+
+    pub fn to_str(&self) -> String {
+        let sum = self
+            .letters
+            .iter()
+            .map(|(_, v, m)| {
+                if *m > 1 {
+                    format!("{}*{}", m, v)
+                } else {
+                    v.to_string()
+                }
+            })
+            .join(" + ");
+
+        if self.word_mult > 1 {
+            format!(
+                "{} = ({}) * {} = {}",
+                self.word, sum, self.word_mult, self.score
+            )
+        } else {
+            format!("{} = {} = {}", self.word, sum, self.score)
         }
     }
 }
 
+/*
+How the score of a move was reached: the main word, every cross-word it formed,
+and the bingo bonus.
+*/
+#[derive(Debug, Clone)]
+pub struct Breakdown {
+    pub main: WordScore,
+    pub cross: Vec<WordScore>,
+    pub bingo: i32,
+    pub total: i32,
+}
+
+impl Breakdown {
+    pub fn none() -> Breakdown {
+        Breakdown {
+            main: WordScore::new(),
+            cross: Vec::new(),
+            bingo: 0,
+            total: 0,
+        }
+    }
+
+    pub fn to_str(&self) -> String {
+        if self.main.letters.is_empty() {
+            return String::from("0");
+        }
+
+        let mut parts = vec![self.main.to_str()];
+        for w in self.cross.iter() {
+            parts.push(w.to_str());
+        }
+        if self.bingo > 0 {
+            parts.push(format!("bingo {}", self.bingo));
+        }
+
+        format!("{}; total {}", parts.join("; "), self.total)
+    }
+}
+
 impl PartialEq for Move {
     fn eq(&self, other: &Self) -> bool {
-        self.word == other.word
+        self.typ == other.typ
             && self.position == other.position
             && self.direction == other.direction
+            && self.tiles == other.tiles
         // && self.score == other.score
         // && self.evaluation == other.evaluation
     }
 }
 
-impl Iterator for IterMove {
-    type Item = (Position, char);
+impl Eq for Move {}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self._m.position.tick_n(self._m.direction, self._curr) {
-            Some(p) => match self._m.word.chars().nth(self._curr as usize) {
-                Some(c) => {
-                    self._curr += 1;
-                    Some((p, c))
-                }
-                None => None,
-            },
-            None => None,
-        }
+impl Hash for Move {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.typ.hash(state);
+        self.position.hash(state);
+        self.direction.hash(state);
+        self.tiles.hash(state);
     }
 }
 
@@ -382,9 +561,9 @@ pub fn letter_with_score(c: &char, bag: &Bag) -> String {
     format!("{}{}", c, from_u32(0x2080 + bag.score(*c) as u32).unwrap())
 }
 
-pub fn rack_to_string(rack: Vec<char>, bag: &Bag) -> String {
-    let top = format!("┌{}────┐", "────┬".repeat(6));
-    let bot = format!("└{}────┘", "────┴".repeat(6));
+pub fn rack_to_string(rack: Vec<char>, bag: &Bag, size: usize) -> String {
+    let top = format!("┌{}────┐", "────┬".repeat(size - 1));
+    let bot = format!("└{}────┘", "────┴".repeat(size - 1));
 
     let mut letters = String::new();
     for c in rack.iter() {
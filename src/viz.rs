@@ -1,20 +1,28 @@
-use crate::board::STATE;
+use crate::board::{Board, Rules, STATE};
+use crate::clock::{Clock, TimeControl};
+use crate::endgame;
 use crate::game::Game;
-use crate::utils::{to_word, write_to_file, Direction, Move, Position, Type as MoveType, ALPH};
+use crate::player::Player;
+use crate::preendgame::{self, PreEndgameResult};
+use crate::strategy::{Budget, Strategy};
+use crate::utils::{write_to_file, Direction, ItemRemovable, Move, Position};
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::TryInto;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
 
 use gdk::RGBA;
 use glib::Type;
 use gtk::prelude::*;
 use gtk::{
     Adjustment, Align, Button, DrawingArea, EventBox, Grid, Label, ListStore, Notebook,
-    ScrolledWindow, StateFlags, TreeView, TreeViewColumn, Viewport, Widget as GTKWidget,
+    ScrolledWindow, StateFlags, TreeView, TreeViewColumn, Widget as GTKWidget,
 };
 use gtk::{Inhibit, Window, WindowType};
 use relm::{timeout, IsA, Relm, Update, Widget};
 use relm_derive::Msg;
-use std::cmp::max;
 
 const GREY: RGBA = RGBA {
     red: 0.38,
@@ -38,7 +46,9 @@ pub enum Msg {
     Type(u32),
     SetMove(usize),
     GenChoices,
+    PreEndgame,
     NewGame,
+    SaveGame,
     ItemSelect,
 }
 
@@ -114,9 +124,11 @@ struct Win {
     back_colors: HashMap<char, RGBA>,
     relm: Relm<Win>,
     click_data: ClickData,
-    out: String, // gcg output
     out_nice: String,
-    moves_generated: Vec<(Position, Direction)>,
+    ended: bool, // the ending is shown
+    moves_generated: Vec<Move>,
+    scores: Rc<RefCell<Vec<Vec<i32>>>>, // each player's score after each of their moves, for the graph
+    analysis: Option<(Player, Receiver<Vec<PreEndgameResult>>)>, // a pre-endgame being worked out, and for whom
 }
 
 impl Win {
@@ -152,10 +164,9 @@ impl Win {
     }
 
     fn place(&mut self, m: &Move, color: &str, force: bool) {
-        let last = self.model.get_last_state();
-        for (p, _) in m.iter() {
-            if force || "#^+-*.".contains(last.0[p.row][p.col]) {
-                self.set(p, color);
+        for t in m.tiles.iter() {
+            if force || !t.on_board {
+                self.set(t.position, color);
             }
         }
     }
@@ -167,8 +178,15 @@ impl Win {
                 let at = self.model.get_board().at_position(p);
                 if first {
                     let l = Label::new(Some(" "));
-                    l.override_background_color(StateFlags::empty(), Some(&self.colors[&at]));
+                    // color by the square underneath, since a loaded game may already have tiles
+                    l.override_background_color(
+                        StateFlags::empty(),
+                        Some(&self.colors[&STATE[row][col]]),
+                    );
                     self.board.attach(&l, row as i32, col as i32, 1, 1);
+                    if self.model.get_board().is_letter(p) {
+                        self.set(p, "white");
+                    }
                 }
                 /* else if self.model.get_board().is_anchor(p) {
                     let l = self.get(p.col as i32, p.row as i32);
@@ -203,7 +221,7 @@ impl Win {
             let s = self.model.get_board().bag.score(a);
             self.lset(l, "white", a, s, &GREY);
         }
-        for i in r.len()..7 {
+        for i in r.len()..self.model.get_board().rules.rack_size {
             let l = self
                 .rack
                 .get_child_at(i as i32, 0)
@@ -220,23 +238,21 @@ impl Win {
     }
 
     fn update_rack_for(&mut self, m: &Move) {
-        let s = self.model.get_last_state().0;
-        let mut word = to_word(&m.word.chars().collect());
+        // rack tiles the move used, with blanks as '?'
+        let mut used: Vec<char> = m
+            .reals()
+            .iter()
+            .map(|c| if c.is_lowercase() { '?' } else { *c })
+            .collect();
 
         for w in self.rack.get_children() {
             let l = w.dynamic_cast::<Label>().ok().unwrap();
             let c = l.get_text().as_str().chars().nth(0).unwrap();
             let score = self.model.get_board().bag.score(c);
             let mut set = "white";
-            for (p, letter) in m.iter() {
-                if letter == c && s[p.row][p.col] != letter {
-                    if let Some(i) = ALPH.find(c) {
-                        if word[i] > 0 {
-                            word[i] -= 1;
-                            set = "yellow";
-                        }
-                    }
-                }
+            if used.contains(&c) {
+                used._remove_item(c);
+                set = "yellow";
             }
             self.lset(l, set, c, score, &GREY);
         }
@@ -249,8 +265,129 @@ impl Win {
         self.update_rack_for(&m);
     }
 
+    /*
+    Adds the nth move (see Game::get_move) to the move list and the graph, as a button that
+    shows the board after it. Returns its line for the nice output.
+    */
+    fn add_move(&mut self, n: usize, rack: &str, m: &Move, total: i32) -> String {
+        let seats = self.model.seats();
+        let c = ((n - 1) % seats) as i32;
+        let t = ((n - 1) / seats + 1) as i32;
+
+        let text = if m.exch() {
+            format!(
+                "{:<7}/EXC: -{:<11} +{:<03}/{:<03}",
+                rack,
+                m.word(),
+                m.score,
+                total
+            )
+        } else if m.is_pass() {
+            format!(
+                "{:<7}/PAS: {:<12} +{:<03}/{:<03}",
+                rack, "-", m.score, total
+            )
+        } else {
+            format!(
+                "{:<7}/{:<3}: {:<12} +{:<03}/{:<03}",
+                rack,
+                m.position.to_str(m.direction),
+                m.format(true),
+                m.score,
+                total
+            )
+        };
+
+        self.scores.borrow_mut()[c as usize].push(total);
+
+        let label = Label::new(Some(&text));
+        if c == 0 {
+            label.set_markup(&format!("<span face=\"monospace\">{}. {}</span>", t, text));
+        } else {
+            label.set_markup(&format!("<span face=\"monospace\">{}</span>", text));
+        }
+        let btn = Button::new();
+        btn.add(&label);
+        if !m.exch() && !m.is_pass() {
+            btn.set_tooltip_text(Some(&self.model.breakdown(n).to_str()));
+        }
+        connect!(self.relm, btn, connect_clicked(_), Msg::SetMove(n - 1));
+        self.moves.attach(&btn, c, t, 1, 1);
+
+        text
+    }
+
+    // the moves of a loaded game
+    fn show_history(&mut self) {
+        let mut totals = vec![0; self.model.seats()];
+        for n in 1..self.model.states() {
+            let m = self.model.get_move(n);
+            let rack: String = self.model.get_rack(n).iter().collect();
+            let seat = (n - 1) % self.model.seats();
+            totals[seat] += m.score;
+            for (_, p, a) in self
+                .model
+                .adjustments()
+                .iter()
+                .filter(|(after, _, _)| *after == n)
+            {
+                totals[*p] += a.points();
+            }
+
+            let text = self.add_move(n, &rack, &m, totals[seat]);
+            self.out_nice += &(text + "\n");
+        }
+    }
+
+    // the results of a pre-endgame worked out for p (see Msg::PreEndgame)
+    fn show_preendgame(&mut self, p: &Player, results: Vec<PreEndgameResult>) {
+        self.side_box.set_current_page(Some(1));
+        self.tree_model.clear();
+        self.moves_generated = vec![];
+        for (i, r) in results.iter().enumerate() {
+            let m = &r.m;
+            self.moves_generated.push(Move::of(m));
+            let leave: String = p.leave(m.reals()).iter().collect();
+            let outcome = format!(
+                "wins {:.1}%, {}",
+                r.wins * 100.0,
+                if r.empties { "empties bag" } else { "fishes" }
+            );
+            self.tree_model.insert_with_values(
+                None,
+                &[0, 1, 2, 3, 4, 5, 6],
+                &[
+                    &((i + 1) as u32),
+                    &m.position.to_str(m.direction),
+                    &m.format(true),
+                    &leave,
+                    &m.score,
+                    &r.spread,
+                    &outcome,
+                ],
+            );
+        }
+    }
+
+    // every player's time left, in the headers of the move list
+    fn show_clocks(&mut self) {
+        let clock = match self.model.clock() {
+            Some(c) => c,
+            None => return,
+        };
+        for n in 0..self.model.seats() {
+            let to_move = n == self.model.current && !self.model.finished;
+            let left = Clock::format(clock.left(n, to_move));
+            if let Some(w) = self.moves.get_child_at(n as i32, 0) {
+                if let Ok(l) = w.dynamic_cast::<Label>() {
+                    l.set_text(&format!("Player {} ({})", n + 1, left));
+                }
+            }
+        }
+    }
+
     fn set_state(&mut self, n: usize) {
-        let (m, r, _) = self.model.set_state(n + 1);
+        let (m, r) = self.model.set_state(n + 1);
 
         self.setup_board(false);
         self._update_rack(&r.clone());
@@ -262,13 +399,31 @@ impl Update for Win {
     // Specify the model used for this widget.
     type Model = Game;
     // Specify the model parameter used to init the model.
-    type ModelParam = ();
+    type ModelParam = (
+        Option<String>,
+        Vec<Option<Box<dyn Strategy>>>,
+        Option<TimeControl>,
+    );
     // Specify the type of the messages sent to the update function.
     type Msg = Msg;
 
     // Return the initial model.
-    fn model(_: &Relm<Self>, _: ()) -> Game {
-        Game::default()
+    fn model(
+        _: &Relm<Self>,
+        (load, strategies, clock): (
+            Option<String>,
+            Vec<Option<Box<dyn Strategy>>>,
+            Option<TimeControl>,
+        ),
+    ) -> Game {
+        let names = (1..=strategies.len()).map(|i| format!("p{}", i)).collect();
+        match Game::open(load.as_deref(), names, strategies, Rules::default(), clock) {
+            Ok(game) => game,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
     }
 
     // The model may be updated when a message is received.
@@ -276,10 +431,8 @@ impl Update for Win {
     fn update(&mut self, event: Msg) {
         match event {
             Msg::Tick => {
-                let c = self.model.current as i32;
                 let t = self.model.get_turn() as i32;
                 let mut text = String::new();
-                let mut gcg_text = String::new();
                 let mut write = false;
                 // let play = false;
                 if !self.model.is_over() {
@@ -293,79 +446,53 @@ impl Update for Win {
 
                     let p = self.model.get_current_player();
                     let rack: String = p.rack.iter().collect();
-                    let score = p.score as i32;
-                    let n = p.name.clone();
+                    let score = p.score;
 
-                    let (m, human, gcg, _n_moves) = self.model.do_move(1, false);
+                    let (m, _, _) = self.model.do_move(false);
                     self.model.state -= 1; // dont know why this is necessary
                     self._handle(&m);
                     self.model.state += 1;
                     self.last_move = Move::of(&m);
 
-                    text = format!(
-                        "{:<7}/{:<3}: {:<12} +{:<03}/{:<03}",
-                        rack,
-                        m.position.to_str(m.direction),
-                        human,
-                        m.score,
-                        score + m.score
-                    );
-                    gcg_text = format!(
-                        ">{}: {} {} {} +{} {}",
-                        n,
-                        rack,
-                        m.position.to_str(m.direction),
-                        gcg,
-                        m.score,
-                        score + m.score
-                    );
-                    if m.exch() {
-                        text = format!(
-                            "{:<7}/EXC: -{:<11} +{:<03}/{:<03}",
-                            rack,
-                            m.word,
-                            m.score,
-                            score + m.score
-                        );
-                    }
-
-                    let label = Label::new(Some(&text));
-                    let n = (t * 2 + c - 1) as usize;
-                    if c == 0 {
-                        label.set_markup(&format!(
-                            "<span face=\"monospace\">{}. {}</span>",
-                            t, text
-                        ));
-                    } else {
-                        label.set_markup(&format!("<span face=\"monospace\">{}</span>", text));
-                    }
-                    let btn = Button::new();
-                    btn.add(&label);
-                    connect!(self.relm, btn, connect_clicked(_), Msg::SetMove(n - 1));
-                    self.moves.attach(&btn, c, t, 1, 1);
+                    let n = self.model.states() - 1;
+                    text = self.add_move(n, &rack, &m, score + m.score);
                     write = true;
-                } else if !self.model.finished {
-                    let (end_s, end, n) = self.model.finish();
-                    text = format!("2*({}) +{}/{}", end_s, end, self.model.get_player(n).score);
-                    gcg_text = format!(
-                        ">{}: ({}) +{} {}",
-                        self.model.get_player(n).name,
-                        end_s,
-                        end,
-                        self.model.get_player(n).score
-                    );
-                    let label = Label::new(Some(&text));
-                    self.moves.attach(&label, n, t + 1, 1, 1);
+                } else if !self.ended {
+                    self.ended = true;
+                    let ending = self.model.finish();
+                    let mut lines = Vec::new();
+                    for n in 0..self.model.seats() {
+                        let p = self.model.get_player(n as i32);
+                        if let Some(end) = ending.notation(n) {
+                            self.scores.borrow_mut()[n].push(p.score);
+                            let line = format!("{}/{}", end, p.score);
+                            let label = Label::new(Some(&line));
+                            self.moves.attach(&label, n as i32, t + 1, 1, 1);
+                            lines.push(line);
+                        }
+                    }
+                    lines.push(self.model.result());
+                    text = lines.join("\n");
                     write = true;
                 }
 
                 if write {
-                    self.out += &(gcg_text + "\n");
                     self.out_nice += &(text + "\n");
-                    write_to_file("out.gcg", self.out.clone());
+                    write_to_file("out.gcg", self.model.to_gcg());
                     write_to_file("out.nice", self.out_nice.clone());
                 }
 
+                if let Some((p, rx)) = self.analysis.take() {
+                    match rx.try_recv() {
+                        Ok(results) => self.show_preendgame(&p, results),
+                        Err(mpsc::TryRecvError::Empty) => self.analysis = Some((p, rx)),
+                        Err(mpsc::TryRecvError::Disconnected) => {
+                            println!("the pre-endgame analysis stopped")
+                        }
+                    }
+                }
+
+                self.show_clocks();
                 self.window.show_all();
                 self.graph.queue_draw();
 
@@ -461,19 +588,20 @@ impl Update for Win {
                 self.tree_model.clear();
                 self.moves_generated = vec![];
                 for (i, m) in moves.iter().take(50).enumerate() {
-                    self.moves_generated.push((m.position, m.direction));
+                    self.moves_generated.push(Move::of(m));
                     let pos = m.position.to_str(m.direction);
-                    let leave: String = p.leave(board.reals(m)).iter().collect();
+                    let leave: String = p.leave(m.reals()).iter().collect();
                     self.tree_model.insert_with_values(
                         None,
-                        &[0, 1, 2, 3, 4, 5],
+                        &[0, 1, 2, 3, 4, 5, 6],
                         &[
                             &((i + 1) as u32),
                             &pos,
-                            &board.format(&m, true),
+                            &m.format(true),
                             &leave,
                             &m.score,
                             &m.eval(1.0, eval_val),
+                            &board.breakdown(m).to_str(),
                         ],
                     );
                 }
@@ -482,7 +610,46 @@ impl Update for Win {
                     self.model.set_state(self.model.state + 1);
                 }
             }
+            Msg::PreEndgame => {
+                // every draw of the last few tiles, for the player to move now
+                let bag = self.model.get_board().bag.distribution.len();
+                if self.model.is_over() || bag == 0 || bag > 3 {
+                    println!("pre-endgame needs 1 to 3 tiles in the bag");
+                    return;
+                }
+                if self.model.seats() != 2 {
+                    println!("pre-endgame needs a two player game");
+                    return;
+                }
+                if self.analysis.is_some() {
+                    println!("still analyzing the last pre-endgame");
+                    return;
+                }
+
+                let p = self.model.get_current_player().clone();
+                let opp = self.model.get_player(((self.model.current + 1) % 2) as i32);
+                let spread = p.score - opp.score;
+                let board = self.model.get_board();
+                let (state, rules, rack) = (board.save_state(), board.rules, p.rack.clone());
+                let (tx, rx) = mpsc::channel();
+                // on a board of its own, so the window keeps responding; Tick picks up the results
+                thread::spawn(move || {
+                    let mut board = Board::default();
+                    board.rules = rules;
+                    board.set_state(&state);
+                    let budget = Budget::time(preendgame::TIME);
+                    let results =
+                        preendgame::analyze(&mut board, &rack, spread, 10, endgame::DEPTH, budget);
+                    let _ = tx.send(results);
+                });
+                self.analysis = Some((p, rx));
+                println!("analyzing the pre-endgame");
+            }
             Msg::NewGame => println!("new game"),
+            Msg::SaveGame => match self.model.save("game.json") {
+                Ok(()) => println!("saved to game.json"),
+                Err(e) => println!("{}", e),
+            },
             Msg::ItemSelect => {
                 let selection = self.options.get_selection();
                 if let Some((list_model, iter)) = selection.get_selected() {
@@ -493,28 +660,6 @@ impl Update for Win {
                         .unwrap()
                         .unwrap()
                         - 1) as usize;
-                    let (position, direction) = self.moves_generated[index];
-
-                    let word = list_model
-                        .get_value(&iter, 2)
-                        .get::<String>()
-                        .ok()
-                        .unwrap()
-                        .unwrap()
-                        .replace("(", "")
-                        .replace(")", "");
-                    let score = list_model
-                        .get_value(&iter, 4)
-                        .get::<u8>()
-                        .ok()
-                        .unwrap()
-                        .unwrap() as i32;
-                    let evaluation = list_model
-                        .get_value(&iter, 5)
-                        .get::<f32>()
-                        .ok()
-                        .unwrap()
-                        .unwrap();
 
                     let shift = self.model.is_over();
                     let mut zero = false;
@@ -526,14 +671,7 @@ impl Update for Win {
                         self.model.set_state(self.model.state - 1);
                     }
 
-                    let m = &Move {
-                        word,
-                        position,
-                        direction,
-                        score,
-                        evaluation,
-                        typ: MoveType::Play,
-                    };
+                    let m = &Move::of(&self.moves_generated[index]);
                     self.model.get_board_mut().place_move(m);
 
                     self.setup_board(false);
@@ -737,10 +875,10 @@ impl Widget for Win {
         moves.set_column_spacing(10);
         moves.set_border_width(3);
 
-        let l1 = Label::new(Some("Player 1"));
-        moves.attach(&l1, 0, 0, 1, 1);
-        let l2 = Label::new(Some("Player 2"));
-        moves.attach(&l2, 1, 0, 1, 1);
+        for n in 0..model.seats() {
+            let l = Label::new(Some(&format!("Player {}", n + 1)));
+            moves.attach(&l, n as i32, 0, 1, 1);
+        }
 
         let moves_container = scroll(&moves);
 
@@ -751,6 +889,7 @@ impl Widget for Win {
             Type::String, // Leave
             Type::U8,     // Score
             Type::F32,    // Eval
+            Type::String, // Breakdown
         ]);
 
         let options = TreeView::with_model(&tree_model);
@@ -765,6 +904,7 @@ impl Widget for Win {
         append_column("Leave", &mut columns, &options, None);
         append_column("Score", &mut columns, &options, None);
         append_column("Eval", &mut columns, &options, None);
+        append_column("Breakdown", &mut columns, &options, None);
 
         connect!(relm, options, connect_cursor_changed(_), Msg::ItemSelect);
 
@@ -784,142 +924,26 @@ impl Widget for Win {
         rack.set_column_homogeneous(true);
         rack.set_halign(Align::Fill);
         rack.set_border_width(5);
-        for i in 0..7 {
+        for i in 0..model.get_board().rules.rack_size {
             let l = Label::new(Some(" "));
             l.override_background_color(StateFlags::empty(), Some(&GREY));
-            rack.attach(&l, i, 0, 1, 1);
+            rack.attach(&l, i as i32, 0, 1, 1);
         }
 
+        let scores = Rc::new(RefCell::new(vec![Vec::new(); model.seats()]));
         let graph = DrawingArea::new();
+        let graph_scores = Rc::clone(&scores);
         graph.connect_draw(move |widget, cr| {
-            let children = widget
-                // get parent as grid
-                .get_parent()
-                .unwrap()
-                .dynamic_cast::<Grid>()
-                .ok()
-                .unwrap()
-                // get moves window
-                .get_children()
-                .iter()
-                .filter(|x| x.is::<Notebook>())
-                .nth(0) // notebook
-                .unwrap()
-                .clone()
-                .dynamic_cast::<Notebook>()
-                .ok()
-                .unwrap()
-                .get_children()[0] /*
-                .iter()
-                .filter(|x| x.is::<ScrolledWindow>())
-                .nth(0)
-                .unwrap()*/
-                .clone()
-                .dynamic_cast::<ScrolledWindow>()
-                .ok()
-                .unwrap()
-                // get actual moves object
-                .get_children()[0]
-                .clone()
-                .dynamic_cast::<Viewport>()
-                .ok()
-                .unwrap()
-                .get_children()[0]
-                .clone()
-                .dynamic_cast::<Grid>()
-                .ok()
-                .unwrap()
-                // get children of moves object
-                .get_children();
-            let (s1, s2): (Vec<(usize, i32)>, Vec<(usize, i32)>) = children
-                .iter()
-                // get buttons, which contain the moves
-                .map(|x| x.clone().dynamic_cast::<Button>())
-                .filter(|x| match x {
-                    Ok(_) => true,
-                    _ => false,
-                })
-                .map(|x| {
-                    x
-                        // get text on label
-                        .ok()
-                        .unwrap()
-                        .get_children()[0]
-                        .clone()
-                        .dynamic_cast::<Label>()
-                        .ok()
-                        .unwrap()
-                        .get_text()
-                        .as_str()
-                        // get score from text
-                        .split("+")
-                        .nth(1)
-                        .unwrap()
-                        .split("/")
-                        .nth(1)
-                        .unwrap()
-                        .parse::<i32>()
-                        .unwrap()
-                })
-                // split into player 1 and player 2
-                .rev()
-                .enumerate()
-                .collect::<Vec<(usize, i32)>>()
-                .iter()
-                .partition(|(i, _)| i % 2 == 0);
-
-            // remove partition artifacts
-            let mut s1: Vec<i32> = s1.iter().map(|x| x.1).collect();
-            let mut s2: Vec<i32> = s2.iter().map(|x| x.1).collect();
-
-            let end = match children
-                .iter() // match because end may not exist (e.g. game may not be over)
-                // get all labels
-                .map(|x| x.clone().dynamic_cast::<Label>())
-                .filter(|x| match x {
-                    Ok(_) => true,
-                    _ => false,
-                })
-                // filter for labels with "/" (there should only be one, the one we want)
-                .map(|x| x.ok().unwrap().get_text())
-                .filter(|x| x.as_str().contains("/"))
-                // take label we found
-                .nth(0)
-            {
-                // extract score
-                Some(s) => s.split("/").nth(1).unwrap().parse::<i32>().unwrap(),
-                _ => 0,
-            };
-
-            // add end score to correct array
-            if end != 0 {
-                if s1.len() > s2.len() {
-                    s1.push(end);
-                } else {
-                    s2.push(end);
-                }
-            }
+            let mut lists = graph_scores.borrow().clone();
 
             // make same length (fill with last value)
-            for _ in 0..2 {
-                // note: the following annoying dance
-                // is required because vecs can't be borrowed
-                // mutably and immutably. goddamn it.
-                // https://github.com/rust-lang/rust/issues/59159
-                if s1.len() < s2.len() {
-                    if let Some(l) = s1.last() {
-                        let l2 = l.clone();
-                        s1.push(l2);
-                    }
-                } else if s1.len() > s2.len() {
-                    if let Some(l) = s2.last() {
-                        let l2 = l.clone();
-                        s2.push(l2);
-                    }
-                }
+            let len = lists.iter().map(|l| l.len()).max().unwrap_or(0);
+            for l in lists.iter_mut() {
+                let last = l.last().cloned().unwrap_or(0);
+                l.resize(len, last);
             }
 
-            let top = max(s1.iter().max(), s2.iter().max()).unwrap() + 10;
+            let top = lists.iter().flatten().max().cloned().unwrap_or(0).max(0) + 10;
 
             let width: f64 = widget.get_allocated_width() as f64;
             let height: f64 = widget.get_allocated_height() as f64;
@@ -931,20 +955,22 @@ impl Widget for Win {
 
             cr.set_line_width(1.0);
 
-            let draw = |list: Vec<i32>| {
+            // red, blue, green and orange, in seat order
+            let colors = [
+                (1.0, 0.0, 0.0),
+                (0.0, 0.0, 1.0),
+                (0.0, 0.6, 0.0),
+                (1.0, 0.5, 0.0),
+            ];
+            for (list, (r, g, b)) in lists.iter().zip(colors.iter()) {
+                cr.set_source_rgb(*r, *g, *b);
                 cr.move_to(0.0, height);
-                let dx = width / (s1.len() as f64);
+                let dx = width / (len.max(1) as f64);
                 for (i, n) in list.iter().enumerate() {
                     cr.line_to(dx * ((i + 1) as f64), height - m * (*n as f64));
                 }
                 cr.stroke();
-            };
-
-            cr.set_source_rgb(1.0, 0.0, 0.0);
-            draw(s1.clone());
-
-            cr.set_source_rgb(0.0, 0.0, 1.0);
-            draw(s2.clone());
+            }
 
             Inhibit(false)
         });
@@ -965,6 +991,16 @@ impl Widget for Win {
         connect!(relm, game_btn, connect_clicked(_), Msg::NewGame);
         button_box.attach(&game_btn, 1, 0, 1, 1);
 
+        let save_btn = Button::new();
+        save_btn.add(&Label::new(Some("Save Game")));
+        connect!(relm, save_btn, connect_clicked(_), Msg::SaveGame);
+        button_box.attach(&save_btn, 2, 0, 1, 1);
+
+        let pre_btn = Button::new();
+        pre_btn.add(&Label::new(Some("Pre-endgame")));
+        connect!(relm, pre_btn, connect_clicked(_), Msg::PreEndgame);
+        button_box.attach(&pre_btn, 3, 0, 1, 1);
+
         let grid = Grid::new();
         grid.set_hexpand(true);
         grid.set_vexpand(true);
@@ -980,13 +1016,10 @@ impl Widget for Win {
         grid.attach(&rack, 4, 17, 7, 1);
         grid.attach(&graph, 13, 11, 10, 5);
 
-        let out = format!(
-            "#character-encoding UTF-8\n#player1 {n1} {n1}\n#player2 {n2} {n2}\n",
-            n1 = "Bot 1",
-            n2 = "Bot 2"
-        );
-
-        let out_nice = "Bot 1 vs Bot 2\n".to_string();
+        let bots = (1..=model.seats())
+            .map(|n| format!("Bot {}", n))
+            .collect::<Vec<String>>();
+        let out_nice = format!("{}\n", bots.join(" vs "));
 
         let window = Window::new(WindowType::Toplevel);
         window.add(&grid);
@@ -1022,17 +1055,24 @@ impl Widget for Win {
             back_colors,
             relm: relm.clone(),
             click_data: ClickData::new(),
-            out,
             out_nice,
+            ended: false,
             moves_generated: vec![],
+            scores,
+            analysis: None,
         };
 
+        win.show_history();
         win.setup_board(true);
 
         win
     }
 }
 
-pub fn main() {
-    Win::run(()).unwrap();
+pub fn main(
+    load: Option<String>,
+    strategies: Vec<Option<Box<dyn Strategy>>>,
+    clock: Option<TimeControl>,
+) {
+    Win::run((load, strategies, clock)).unwrap();
 }